@@ -1,10 +1,14 @@
-use std::{error::Error, str::FromStr};
+use std::{
+    error::Error,
+    fmt,
+    io::{self, IsTerminal},
+    str::FromStr,
+};
 
 use ansi_term::Style;
 use chrono::{Datelike, Local, NaiveDate, Weekday};
 use clap::Parser;
 use itertools::Itertools;
-use regex::RegexBuilder;
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
@@ -14,7 +18,8 @@ pub struct Args {
     /// year
     // 何年のカレンダーを表示するか
     // 指定されない場合は今年が設定される
-    #[arg(short, long)]
+    // 負の数も parse_year で範囲外のエラーにするため、"-" で始まる値を受け付ける
+    #[arg(short, long, allow_hyphen_values = true)]
     year: Option<String>,
 
     /// month
@@ -22,6 +27,16 @@ pub struct Args {
     // 指定されない場合は12月分すべてが表示される
     #[arg(short, long)]
     month: Option<String>,
+
+    /// Print the whole year one month per row
+    // 年全体を表示するときに、3か月ずつ横に並べずに縦に1列で並べる
+    #[arg(short = '1', long)]
+    one_column: bool,
+
+    /// Do not highlight today
+    // 端末以外に出力するときは指定しなくても反転しない
+    #[arg(long)]
+    no_highlight: bool,
 }
 
 // ----------------------------------------------------------------------
@@ -32,11 +47,16 @@ impl Args {
         let year = self
             .year
             .as_ref()
-            .map_or(Ok(today.year()), |y| parse_year(&y))?;
+            .map_or(Ok(Year(today.year())), |y| parse_year(y))?;
 
-        let month = self.month.as_ref().map(|m| parse_month(&m)).transpose()?;
+        let month = self.month.as_ref().map(|m| parse_month(m)).transpose()?;
 
-        Ok(Config { year, month })
+        Ok(Config {
+            year,
+            month,
+            one_column: self.one_column,
+            highlight: !self.no_highlight && io::stdout().is_terminal(),
+        })
     }
 }
 
@@ -48,34 +68,53 @@ pub fn get_config() -> MyResult<Config> {
 // ----------------------------------------------------------------------
 #[derive(Debug)]
 pub struct Config {
-    year: i32,
-    month: Option<u32>,
+    year: Year,
+    month: Option<Month>,
+    one_column: bool,
+    // today を反転表示するか
+    highlight: bool,
 }
 
 // ----------------------------------------------------------------------
 pub fn run(config: Config) -> MyResult<()> {
     let today = Local::now().date_naive();
+    let hl = config.highlight;
+    let year = config.year.value();
 
     match config.month {
+        None if config.one_column => {
+            // year全体を1か月ずつ縦に並べて表示する
+            // 各月のtitleにはyearは表示せず、先頭に月の幅に合わせたyearの見出しを置く
+            println!("{}", center_title(year.to_string()));
+            for month in 1..=12 {
+                format_month(year, month, false, today, hl)?
+                    .iter()
+                    .for_each(|line| println!("{}", line));
+                println!()
+            }
+        }
         None => {
             // year全体を表示する
             //　各月のtitleにはyearは表示しない
             let header = format!(
                 "{}{}{}",
                 " ".repeat(28),
-                config.year.to_string(),
-                " ".repeat(66 - 28 - config.year.to_string().len())
+                year,
+                " ".repeat(66 - 28 - year.to_string().len())
             );
             println!("{}", header);
-            let body = (1..=12)
-                .map(|month| format_month(config.year, month, false, today))
+            let months = (1..=12)
+                .map(|month| format_month(year, month, false, today, hl))
+                .collect::<MyResult<Vec<_>>>()?;
+            let body = months
+                .into_iter()
                 .chunks(3)
                 .into_iter()
                 .map(|vecs| {
                     vecs.into_iter()
                         .reduce(|acc, row| {
                             acc.into_iter()
-                                .zip(row.into_iter())
+                                .zip(row)
                                 .map(|(a, b)| a + &b)
                                 .collect_vec()
                         })
@@ -84,13 +123,13 @@ pub fn run(config: Config) -> MyResult<()> {
                 .collect::<Vec<_>>();
             body.iter().for_each(|three_month| {
                 three_month.iter().for_each(|line| println!("{}", line));
-                println!("")
+                println!()
             });
         }
         Some(month) => {
             // 指定された月だけを表示する
             // titleにyearも表示する
-            let calendar = format_month(config.year, month, true, today);
+            let calendar = format_month(year, month.number(), true, today, hl)?;
             calendar.iter().for_each(|line| println!("{}", line));
         }
     }
@@ -98,9 +137,9 @@ pub fn run(config: Config) -> MyResult<()> {
 }
 
 // ----------------------------------------------------------------------
-fn parse_year(year: &str) -> MyResult<i32> {
+pub fn parse_year(year: &str) -> MyResult<Year> {
     match parse_int::<i32>(year) {
-        Ok(year) if 1 <= year && year <= 9999 => Ok(year),
+        Ok(year) if (1..=9999).contains(&year) => Ok(Year(year)),
         Ok(year) => Err(From::from(format!(
             "year \"{}\" not in the range 1 through 9999",
             year
@@ -110,34 +149,112 @@ fn parse_year(year: &str) -> MyResult<i32> {
 }
 
 // ----------------------------------------------------------------------
-fn parse_month(month: &str) -> MyResult<u32> {
-    match parse_int::<u32>(month) {
-        Ok(month) if 1 <= month && month <= 12 => Ok(month),
-        Ok(month) => Err(From::from(format!(
-            "month \"{}\" not in the range 1 through 12",
-            month
-        ))),
-        Err(_e) => {
-            let re = RegexBuilder::new(&format!("^{}", month))
-                .case_insensitive(true)
-                .build()
-                .map_err(|_| format!("Invalid pattern \"{}\"", month))?;
-
-            let filtered_month = MONTHS
-                .iter()
-                .enumerate()
-                .filter(|(_, month)| re.is_match(month))
-                .collect::<Vec<_>>();
+pub fn parse_month(month: &str) -> MyResult<Month> {
+    month.parse()
+}
+
+// ----------------------------------------------------------------------
+/// 1 から 9999 までの、カレンダーを表示できる年
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Year(i32);
+
+impl Year {
+    pub fn value(&self) -> i32 {
+        self.0
+    }
+}
+
+impl FromStr for Year {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_year(s)
+    }
+}
 
-            if filtered_month.len() == 1 {
-                Ok((filtered_month[0].0 + 1) as u32)
-            } else {
-                Err(format!("Invalid month \"{}\"", month).into())
+impl fmt::Display for Year {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// ----------------------------------------------------------------------
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Month {
+    January,
+    February,
+    March,
+    April,
+    May,
+    June,
+    July,
+    August,
+    September,
+    October,
+    November,
+    December,
+}
+
+impl Month {
+    pub const ALL: [Month; 12] = [
+        Month::January,
+        Month::February,
+        Month::March,
+        Month::April,
+        Month::May,
+        Month::June,
+        Month::July,
+        Month::August,
+        Month::September,
+        Month::October,
+        Month::November,
+        Month::December,
+    ];
+
+    /// 1 (January) から 12 (December)
+    pub fn number(&self) -> u32 {
+        *self as u32 + 1
+    }
+
+    pub fn name(&self) -> &'static str {
+        MONTHS[*self as usize]
+    }
+}
+
+// 1 から 12 の数か、月の名前の先頭部分 (大文字小文字は区別しない) を受け付ける
+// 先頭部分が複数の月に当てはまる場合 ("ju" など) はエラーにする
+impl FromStr for Month {
+    type Err = Box<dyn Error>;
+
+    fn from_str(month: &str) -> Result<Self, Self::Err> {
+        match parse_int::<u32>(month) {
+            Ok(num) if (1..=12).contains(&num) => Ok(Month::ALL[num as usize - 1]),
+            Ok(num) => Err(From::from(format!(
+                "month \"{}\" not in the range 1 through 12",
+                num
+            ))),
+            Err(_e) => {
+                let prefix = month.to_lowercase();
+                let matched = Month::ALL
+                    .into_iter()
+                    .filter(|m| m.name().to_lowercase().starts_with(&prefix))
+                    .collect::<Vec<_>>();
+
+                match matched.as_slice() {
+                    [month] => Ok(*month),
+                    _ => Err(format!("Invalid month \"{}\"", month).into()),
+                }
             }
         }
     }
 }
 
+impl fmt::Display for Month {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
 // ----------------------------------------------------------------------
 const MONTHS: [&str; 12] = [
     "January",
@@ -161,25 +278,24 @@ fn parse_int<T: FromStr>(val: &str) -> MyResult<T> {
 }
 
 // ----------------------------------------------------------------------
-fn format_month(year: i32, month: u32, print_year: bool, today: NaiveDate) -> Vec<String> {
+// chrono で扱えない日付になる場合は panic せずにエラーを返す
+fn format_month(
+    year: i32,
+    month: u32,
+    print_year: bool,
+    today: NaiveDate,
+    highlight: bool,
+) -> MyResult<Vec<String>> {
     // Todo: ここから
-    // year, month のカレンダーを表示する。today が含まれるならそこだけ反転する
+    // year, month のカレンダーを表示する。highlight のとき today が含まれるならそこだけ反転する
     // 必ず 8行22列
     // year monthに関しては、21列の真ん中にする（長さが奇数の場合は左にずれる）
     let title = if print_year {
         format!("{} {}", MONTHS[(month - 1) as usize], year)
     } else {
-        format!("{}", MONTHS[(month - 1) as usize])
+        MONTHS[(month - 1) as usize].to_string()
     };
-    // title は 1 行目の 11-(2/len) 列から始まる
-    // 11-(len/2)-1 個の " " + title + 13-len+(len/2) 個の " "
-    let len = title.chars().count();
-    let top_line = [
-        " ".repeat(11 - ((len + 1) / 2) - 1),
-        title,
-        " ".repeat(12 + ((len + 1) / 2) - len),
-    ]
-    .join("");
+    let top_line = center_title(title);
     let week = String::from("Su Mo Tu We Th Fr Sa  ");
 
     // 1~最終日までループしてVec<String> を作ってく
@@ -188,28 +304,31 @@ fn format_month(year: i32, month: u32, print_year: bool, today: NaiveDate) -> Ve
     // 全ての日にちにおいて、土曜日だけ特殊処理が入る
     let mut days = vec![];
     let mut line = String::from("");
-    let last_day = last_day_in_month(year, month).unwrap();
+    let last_day = last_day_in_month(year, month)?;
 
     for i in 1..=(last_day.day() as usize) {
-        let date = NaiveDate::from_ymd_opt(year, month, i as u32).unwrap();
+        let date = NaiveDate::from_ymd_opt(year, month, i as u32).ok_or(format!(
+            "err: NaiveDateの取得 year: {}, month: {}, day: {}",
+            year, month, i
+        ))?;
         let weekday = date.weekday();
         if i == 1 {
             let offset = weekday.num_days_from_sunday();
             line = "   ".repeat(offset as usize);
-            line = format!("{}{} ", line, print_day(today, year, month, i));
+            line = format!("{}{} ", line, print_day(today, year, month, i, highlight));
             if weekday == Weekday::Sat {
                 line = format!("{} ", line);
                 days.push(line);
                 line = "".to_string();
             }
         } else if i == last_day.day() as usize {
-            line = format!("{}{} ", line, print_day(today, year, month, i));
+            line = format!("{}{} ", line, print_day(today, year, month, i, highlight));
             let offset = 6 - weekday.num_days_from_sunday();
             line = format!("{}{} ", line, "   ".repeat(offset as usize));
             days.push(line);
             line = "".to_string()
         } else {
-            line = format!("{}{} ", line, print_day(today, year, month, i));
+            line = format!("{}{} ", line, print_day(today, year, month, i, highlight));
             if weekday == Weekday::Sat {
                 line = format!("{} ", line);
                 days.push(line);
@@ -223,31 +342,45 @@ fn format_month(year: i32, month: u32, print_year: bool, today: NaiveDate) -> Ve
         days.push(" ".repeat(22))
     }
 
-    std::iter::once(top_line)
+    Ok(std::iter::once(top_line)
         .chain(std::iter::once(week))
-        .chain(days.into_iter())
-        .collect()
+        .chain(days)
+        .collect())
+}
+
+// ----------------------------------------------------------------------
+// title を月のカレンダーの幅 (22列) の中央に置く
+fn center_title(title: String) -> String {
+    // title は 1 行目の 11-(2/len) 列から始まる
+    // 11-(len/2)-1 個の " " + title + 13-len+(len/2) 個の " "
+    let len = title.chars().count();
+    [
+        " ".repeat(11 - len.div_ceil(2) - 1),
+        title,
+        " ".repeat(12 + len.div_ceil(2) - len),
+    ]
+    .join("")
 }
 
 // ----------------------------------------------------------------------
 fn last_day_in_month(year: i32, month: u32) -> MyResult<NaiveDate> {
     let (y, m) = if month == 12 {
-        (year + 1, 1)
+        (year.checked_add(1), 1)
     } else {
-        (year, month + 1)
+        (Some(year), month + 1)
     };
 
-    Ok(NaiveDate::from_ymd_opt(y, m, 1)
-        .ok_or(format!("err: NaiveDateの取得 year: {}, month: {}", year, month).into())
-        .and_then(|date| date.pred_opt().ok_or(format!("err: NaiveDateの前日の取得")))?)
+    Ok(y.and_then(|y| NaiveDate::from_ymd_opt(y, m, 1))
+        .ok_or(format!("err: NaiveDateの取得 year: {}, month: {}", year, month))
+        .and_then(|date| date.pred_opt().ok_or("err: NaiveDateの前日の取得".to_string()))?)
 }
 
 // ----------------------------------------------------------------------
-fn print_day(today: NaiveDate, year: i32, month: u32, day: usize) -> String {
+fn print_day(today: NaiveDate, year: i32, month: u32, day: usize, highlight: bool) -> String {
     let style = Style::new().reverse();
     let num_str = day.to_string();
 
-    if today.year() == year && today.month() == month && today.day() == day as u32 {
+    if highlight && today.year() == year && today.month() == month && today.day() == day as u32 {
         if num_str.len() == 1 {
             style.paint(format!(" {}", num_str)).to_string()
         } else {
@@ -263,7 +396,7 @@ fn print_day(today: NaiveDate, year: i32, month: u32, day: usize) -> String {
 mod tests {
     use chrono::{Datelike, NaiveDate};
 
-    use crate::{format_month, last_day_in_month, parse_month, parse_year};
+    use crate::{format_month, last_day_in_month, parse_month, parse_year, Month, Year};
 
     use super::parse_int;
 
@@ -286,11 +419,11 @@ mod tests {
     fn test_parse_year() {
         let res = parse_year("1");
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), 1i32);
+        assert_eq!(res.unwrap(), Year(1));
 
         let res = parse_year("9999");
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), 9999i32);
+        assert_eq!(res.unwrap().value(), 9999);
 
         let res = parse_year("0");
         assert!(res.is_err());
@@ -308,11 +441,11 @@ mod tests {
     fn test_parse_month() {
         let res = parse_month("1");
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), 1);
+        assert_eq!(res.unwrap(), Month::January);
 
         let res = parse_month("12");
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), 12);
+        assert_eq!(res.unwrap().number(), 12);
 
         let res = parse_month("0");
         assert!(res.is_err());
@@ -330,11 +463,11 @@ mod tests {
 
         let res = parse_month("jan");
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), 1);
+        assert_eq!(res.unwrap(), Month::January);
 
         let res = parse_month("JaN");
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), 1);
+        assert_eq!(res.unwrap(), Month::January);
 
         let res = parse_month("ju");
         assert!(res.is_err());
@@ -344,11 +477,47 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_month_from_str() {
+        assert_eq!("jan".parse::<Month>().unwrap(), Month::January);
+        assert_eq!("Sept".parse::<Month>().unwrap(), Month::September);
+        assert_eq!("december".parse::<Month>().unwrap().number(), 12);
+
+        // 複数の月に当てはまる先頭部分はエラー
+        for ambiguous in ["ju", "ma", "a", ""] {
+            let res = ambiguous.parse::<Month>();
+            assert!(res.is_err());
+            assert_eq!(
+                res.unwrap_err().to_string(),
+                format!("Invalid month \"{}\"", ambiguous)
+            );
+        }
+
+        // 正規表現としては解釈しない
+        assert!(".*".parse::<Month>().is_err());
+        assert!("(".parse::<Month>().is_err());
+    }
+
+    #[test]
+    fn test_month_number_name() {
+        for (i, month) in Month::ALL.iter().enumerate() {
+            assert_eq!(month.number(), i as u32 + 1);
+        }
+        assert_eq!(Month::May.name(), "May");
+        assert_eq!(Month::May.to_string(), "May");
+    }
+
     #[test]
     fn test_last_day_in_month() {
         let res = last_day_in_month(2020, 2);
         assert!(res.is_ok());
         assert_eq!(29, res.unwrap().day());
+
+        let res = last_day_in_month(9999, 12);
+        assert!(res.is_ok());
+        assert_eq!(31, res.unwrap().day());
+
+        assert!(last_day_in_month(i32::MAX, 12).is_err());
     }
 
     #[test]
@@ -364,7 +533,10 @@ mod tests {
             "23 24 25 26 27 28 29  ",
             "                      ",
         ];
-        assert_eq!(format_month(2020, 2, true, today), leap_february);
+        assert_eq!(
+            format_month(2020, 2, true, today, true).unwrap(),
+            leap_february
+        );
 
         let may = vec![
             "        May           ",
@@ -376,7 +548,7 @@ mod tests {
             "24 25 26 27 28 29 30  ",
             "31                    ",
         ];
-        assert_eq!(format_month(2020, 5, false, today), may);
+        assert_eq!(format_month(2020, 5, false, today, true).unwrap(), may);
 
         let april_hl = vec![
             "     April 2021       ",
@@ -389,6 +561,34 @@ mod tests {
             "                      ",
         ];
         let today = NaiveDate::from_ymd_opt(2021, 4, 7).unwrap();
-        assert_eq!(format_month(2021, 4, true, today), april_hl);
+        assert_eq!(format_month(2021, 4, true, today, true).unwrap(), april_hl);
+
+        // 扱える最後の年の最後の月
+        let last_december = vec![
+            "   December 9999      ",
+            "Su Mo Tu We Th Fr Sa  ",
+            "          1  2  3  4  ",
+            " 5  6  7  8  9 10 11  ",
+            "12 13 14 15 16 17 18  ",
+            "19 20 21 22 23 24 25  ",
+            "26 27 28 29 30 31     ",
+            "                      ",
+        ];
+        assert_eq!(
+            format_month(9999, 12, true, today, true).unwrap(),
+            last_december
+        );
+
+        // chrono の範囲外の年はエラーになる
+        assert!(format_month(i32::MAX, 12, true, today, true).is_err());
+    }
+
+    #[test]
+    fn test_format_month_no_highlight() {
+        // today が含まれていても反転しない
+        let today = NaiveDate::from_ymd_opt(2021, 4, 7).unwrap();
+        let april = format_month(2021, 4, true, today, false).unwrap();
+        assert!(april.iter().all(|line| !line.contains('\u{1b}')));
+        assert_eq!(april[3], " 4  5  6  7  8  9 10  ");
     }
 }
@@ -1,5 +1,5 @@
 use assert_cmd::Command;
-use predicates::prelude::*;
+use chrono::Datelike;
 use std::{error::Error, fs};
 
 type TestResult = Result<(), Box<dyn Error>>;
@@ -10,7 +10,7 @@ const PRG: &str = "calr";
 #[test]
 fn dies_year_0() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&["-y", "0"])
+        .args(["-y", "0"])
         .assert()
         .failure()
         .stderr("year \"0\" not in the range 1 through 9999\n");
@@ -21,7 +21,7 @@ fn dies_year_0() -> TestResult {
 #[test]
 fn dies_year_13() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&["-y", "10000"])
+        .args(["-y", "10000"])
         .assert()
         .failure()
         .stderr("year \"10000\" not in the range 1 through 9999\n");
@@ -32,7 +32,7 @@ fn dies_year_13() -> TestResult {
 #[test]
 fn dies_invalid_year() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&["-y", "foo"])
+        .args(["-y", "foo"])
         .assert()
         .failure()
         .stderr("Invalid integer \"foo\"\n");
@@ -43,7 +43,7 @@ fn dies_invalid_year() -> TestResult {
 #[test]
 fn dies_month_0() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&["-m", "0"])
+        .args(["-m", "0"])
         .assert()
         .failure()
         .stderr("month \"0\" not in the range 1 through 12\n");
@@ -54,7 +54,7 @@ fn dies_month_0() -> TestResult {
 #[test]
 fn dies_month_13() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&["-m", "13"])
+        .args(["-m", "13"])
         .assert()
         .failure()
         .stderr("month \"13\" not in the range 1 through 12\n");
@@ -65,7 +65,7 @@ fn dies_month_13() -> TestResult {
 #[test]
 fn dies_invalid_month() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&["-m", "foo"])
+        .args(["-m", "foo"])
         .assert()
         .failure()
         .stderr("Invalid month \"foo\"\n");
@@ -92,7 +92,7 @@ fn month_num() -> TestResult {
 
     for (num, month) in expected {
         Command::cargo_bin(PRG)?
-            .args(&["-m", num])
+            .args(["-m", num])
             .assert()
             .success()
             .stdout(predicates::str::contains(month.to_string()));
@@ -119,7 +119,7 @@ fn partial_month() -> TestResult {
 
     for (arg, month) in expected {
         Command::cargo_bin(PRG)?
-            .args(&["-m", arg])
+            .args(["-m", arg])
             .assert()
             .success()
             .stdout(predicates::str::contains(month.to_string()));
@@ -167,3 +167,89 @@ fn test_4_2020() -> TestResult {
 fn test_april_2020() -> TestResult {
     run(&["-y", "2020", "-m", "april"], "tests/expected/4-2020.txt")
 }
+
+// --------------------------------------------------
+#[test]
+fn test_2020_one_column() -> TestResult {
+    run(
+        &["-y", "2020", "--one-column"],
+        "tests/expected/2020.one_column.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn one_column_stacks_twelve_months() -> TestResult {
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["-y", "2021", "-1"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let lines: Vec<_> = stdout.split('\n').collect();
+    // 見出し 1 行 + 12 か月 × (8 行 + 空行)、最後の改行の後の空文字列
+    assert_eq!(lines.len(), 1 + 12 * 9 + 1);
+    assert!(lines.iter().all(|line| line.chars().count() <= 22));
+    let titles: Vec<_> = lines
+        .iter()
+        .skip(1)
+        .step_by(9)
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect();
+    assert_eq!(
+        titles,
+        [
+            "January", "February", "March", "April", "May", "June", "July",
+            "August", "September", "October", "November", "December",
+        ]
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_negative_year() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-y", "-5"])
+        .assert()
+        .failure()
+        .stderr("year \"-5\" not in the range 1 through 9999\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn december_9999() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-y", "9999", "-m", "12"])
+        .assert()
+        .success()
+        .stdout(
+            "   December 9999      \n\
+             Su Mo Tu We Th Fr Sa  \n          1  2  3  4  \n 5  6  7  8  9 10 11  \n\
+             12 13 14 15 16 17 18  \n19 20 21 22 23 24 25  \n26 27 28 29 30 31     \n\
+             \x20                     \n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn no_highlight_this_month() -> TestResult {
+    // 今月には必ず today が含まれるが、反転のエスケープシーケンスは出ない
+    let today = chrono::Local::now();
+    let cmd = Command::cargo_bin(PRG)?
+        .args([
+            "--no-highlight",
+            "-m",
+            &today.month().to_string(),
+            "-y",
+            &today.year().to_string(),
+        ])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    assert!(!stdout.contains('\u{1b}'));
+    assert!(stdout.contains(&format!("{:>2}", today.day())));
+    Ok(())
+}
@@ -1,6 +1,7 @@
 use core::fmt;
+use std::borrow::Cow;
 use std::{error::Error, fs::File};
-use std::io::{self, BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, Write};
 
 use clap::{Parser, ValueEnum};
 
@@ -21,12 +22,49 @@ pub struct Arg {
     )]
     #[clap(value_enum)]
     print_mode: PrintMode,
+
+    /// Suppress repeated empty output lines
+    #[arg(short = 's', long = "squeeze-blank")]
+    squeeze_blank: bool,
+
+    /// Squeeze runs of blank lines, keeping at most N of them in a row (-s keeps 1)
+    #[arg(long = "squeeze-limit", value_name = "N")]
+    squeeze_limit: Option<usize>,
+
+    /// Print a "==> FILE <==" header before each file
+    #[arg(long = "headers")]
+    headers: bool,
+
+    /// Display $ at end of each line
+    #[arg(short = 'E', long = "show-ends")]
+    show_ends: bool,
+
+    /// Display TAB characters as ^I
+    #[arg(short = 'T', long = "show-tabs")]
+    show_tabs: bool,
+
+    /// Print only lines START through END (1-based, inclusive)
+    #[arg(long = "line-range", value_name = "START:END")]
+    line_range: Option<String>,
+
+    /// Print the lines of each file in reverse order, like tac (line numbers stay with their lines)
+    #[arg(long = "reverse")]
+    reverse: bool,
+
+    /// After printing, report the number of lines and bytes read to stderr
+    #[arg(long = "stats")]
+    stats: bool,
 }
 
 impl Arg {
     // parse した arg を config に変換する
-    fn to_config(self) -> Config {
-        Config {
+    #[allow(clippy::wrong_self_convention)]
+    fn to_config(self) -> MyResult<Config> {
+        let line_range = self.line_range
+            .map(|range| parse_line_range(&range))
+            .transpose()?;
+
+        Ok(Config {
             input: {
                 match self.files {
                     None => Input::Stdin,
@@ -34,13 +72,37 @@ impl Arg {
                 }
             },
             print_mode: self.print_mode,
-        }
+            squeeze_limit: match (self.squeeze_blank, self.squeeze_limit) {
+                (_, Some(limit)) => Some(limit),
+                (true, None) => Some(1),
+                (false, None) => None,
+            },
+            headers: self.headers,
+            line_range,
+            show_ends: self.show_ends,
+            show_tabs: self.show_tabs,
+            reverse: self.reverse,
+            stats: self.stats,
+        })
     }
 }
 
+// "START:END" を (START, END) に変換する
+fn parse_line_range(range: &str) -> MyResult<(usize, usize)> {
+    let range_error = || format!("illegal line range: \"{}\"", range);
+    let (start, end) = range.split_once(':').ok_or_else(range_error)?;
+    let start: usize = start.parse().map_err(|_| range_error())?;
+    let end: usize = end.parse().map_err(|_| range_error())?;
+
+    if start == 0 || start > end {
+        return Err(From::from(range_error()));
+    }
+    Ok((start, end))
+}
+
 
 #[derive(ValueEnum, Clone, Debug, Eq, PartialEq)]
-enum PrintMode {
+pub enum PrintMode {
     Normal,
     Number,
     NumberAndNonblank,
@@ -50,6 +112,39 @@ pub struct Config {
     input: Input,
 
     print_mode: PrintMode,
+
+    // None なら空行を圧縮しない
+    squeeze_limit: Option<usize>,
+
+    headers: bool,
+
+    // None なら全ての行を出力する
+    line_range: Option<(usize, usize)>,
+
+    show_ends: bool,
+    show_tabs: bool,
+
+    // ファイルごとに行を逆順に出力する
+    reverse: bool,
+
+    stats: bool,
+}
+
+impl Config {
+    // 指定した出力形式以外はデフォルトの設定
+    fn with_mode(print_mode: PrintMode) -> Config {
+        Config {
+            input: Input::Stdin,
+            print_mode,
+            squeeze_limit: None,
+            headers: false,
+            line_range: None,
+            show_ends: false,
+            show_tabs: false,
+            reverse: false,
+            stats: false,
+        }
+    }
 }
 
 enum Input {
@@ -58,6 +153,7 @@ enum Input {
 }
 
 
+#[allow(dead_code)]
 #[derive(Debug)]
 struct FileOpenError {
     filename: String,
@@ -77,19 +173,21 @@ impl Error for FileOpenError {
 }
 
 pub fn get_config() -> MyResult<Config> {
-    Ok(Arg::parse().to_config())
+    Arg::parse().to_config()
 }
 
-// None なら stdin、 Some(file) なら file への buf_reader を返す
+// None または Some("-") なら stdin、 Some(file) なら file への buf_reader を返す
 fn open(input: Option<&str>) -> MyResult<Box<dyn BufRead>> {
     match input {
-        None => Ok(Box::new(BufReader::new(io::stdin()))),
+        None | Some("-") => Ok(Box::new(BufReader::new(io::stdin()))),
         Some(filename) => Ok(Box::new(BufReader::new(File::open(filename)?))),
     }
 }
 
 pub fn run(config: Config) -> MyResult<()> {
     let mut err_flg = false;
+    let mut out = io::stdout().lock();
+    let mut state = CatState::new();
 
     match &config.input {
         Input::Stdin => {
@@ -98,18 +196,26 @@ pub fn run(config: Config) -> MyResult<()> {
                     eprintln!("Failed to open stdin: {}", err);
                     err_flg = true;
                 },
-                Ok(buf_reader) => cat_file(&config, buf_reader)?
+                Ok(buf_reader) => {
+                    if let Err(err) = cat_file(&config, "-", buf_reader, false, &mut state, &mut out) {
+                        eprintln!("-: {}", err);
+                        err_flg = true;
+                    }
+                }
             }
         }
         Input::Files(files) => {
-            for filename in files {
+            for (i, filename) in files.iter().enumerate() {
                 match open(Some(filename)) {
                     Err(err) => {
                         eprintln!("Failed to open {}: {}", filename, err);
                         err_flg = true;
                     },
                     Ok(buf_reader) => {
-                        cat_file(&config, buf_reader)?
+                        if let Err(err) = cat_file(&config, filename, buf_reader, i > 0, &mut state, &mut out) {
+                            eprintln!("{}: {}", filename, err);
+                            err_flg = true;
+                        }
                     }
                 }
             }
@@ -118,8 +224,12 @@ pub fn run(config: Config) -> MyResult<()> {
     }
 
 
+    if config.stats {
+        eprintln!("{} lines, {} bytes", state.lines_read, state.bytes_read);
+    }
+
     if err_flg {
-        Err(Box::new(io::Error::new(io::ErrorKind::Other, "少なくとも一つのファイルでエラーがありました")))
+        Err(Box::new(io::Error::other("少なくとも一つのファイルでエラーがありました")))
         // Err(From::from("少なくとも1つのファイルでエラーがありました")) ← こっちの方が簡潔だけど、自分でエラーを定義する例として残したいので変えない
     } else {
         Ok(())
@@ -127,31 +237,333 @@ pub fn run(config: Config) -> MyResult<()> {
 }
 
 
-fn cat_file(config: &Config, bufreader: Box<dyn BufRead>) -> MyResult<()> {
-    let mut i = 1;
-    for line in bufreader.lines() {
-        let line = line.unwrap();
-        match config.print_mode {
-            PrintMode::Normal => {
-                println!("{}", line);
+// ファイルをまたいで引き継ぐ状態
+struct CatState {
+    // 次に出力する行番号 (--line-range のときはファイルごとに 1 から数え直す)
+    line_number: usize,
+    // --stats 用に、これまでに読んだ行数とバイト数
+    // 最後の改行のない行も1行と数える。--line-range では範囲の最後の行より後は読まない
+    lines_read: usize,
+    bytes_read: usize,
+}
+
+impl CatState {
+    fn new() -> CatState {
+        CatState { line_number: 1, lines_read: 0, bytes_read: 0 }
+    }
+}
+
+// not_head: 先頭のファイルではない、またその時のみ true
+fn cat_file(
+    config: &Config,
+    filename: &str,
+    bufreader: impl BufRead,
+    not_head: bool,
+    state: &mut CatState,
+    out: &mut impl Write,
+) -> MyResult<()> {
+    // headr と同じく、2つ目以降のファイルのヘッダーの前には空行を出力する
+    if config.headers {
+        if not_head {
+            writeln!(out)?;
+        }
+        writeln!(out, "==> {} <==", filename)?;
+    }
+
+    // --reverse のときは tac と同じくファイル1つ分の行をすべてメモリに溜めてから逆順に出す
+    // 行番号や空行の圧縮、--line-range は元の順番で処理したものなので、番号は行についたまま逆順になる
+    let invalid_utf8 = if config.reverse {
+        let mut lines = Vec::new();
+        let invalid_utf8 = render_with(config, bufreader, state, |line| {
+            lines.push(line);
+            Ok(())
+        })?;
+        for line in lines.iter().rev() {
+            writeln!(out, "{}", line)?;
+        }
+        invalid_utf8
+    } else {
+        render_with(config, bufreader, state, |line| {
+            writeln!(out, "{}", line)?;
+            Ok(())
+        })?
+    };
+
+    if invalid_utf8 {
+        eprintln!("{}: invalid utf-8", filename);
+    }
+
+    Ok(())
+
+}
+
+/// Formats every line of `reader` the way catr prints it under `mode`,
+/// returning the lines (without trailing newlines) instead of printing them.
+pub fn render(reader: impl BufRead, mode: PrintMode) -> MyResult<Vec<String>> {
+    let mut lines = Vec::new();
+    render_with(&Config::with_mode(mode), reader, &mut CatState::new(), |line| {
+        lines.push(line);
+        Ok(())
+    })?;
+    Ok(lines)
+}
+
+// 整形した行を1行ずつ emit に渡す
+// 不正な UTF-8 を含んでいた場合は true を返す
+fn render_with(
+    config: &Config,
+    mut bufreader: impl BufRead,
+    state: &mut CatState,
+    mut emit: impl FnMut(String) -> MyResult<()>,
+) -> MyResult<bool> {
+    // 連続している空行の数
+    let mut blank_run = 0;
+    // 入力ファイルでの行番号
+    let mut line_num = 0;
+    // UTF-8 として不正なバイト列を含んでいたか
+    let mut invalid_utf8 = false;
+    // --line-range の範囲はファイルごとの行を指すので、行番号もファイルごとに数え直す
+    if config.line_range.is_some() {
+        state.line_number = 1;
+    }
+    let mut buf = Vec::new();
+    loop {
+        // 範囲の最後の行まで出したら、次の行は読まずにやめる (stdin も余計に読み進めない)
+        if config.line_range.is_some_and(|(_, end)| line_num >= end) {
+            break;
+        }
+        buf.clear();
+        let bytes = bufreader.read_until(b'\n', &mut buf)?;
+        if bytes == 0 {
+            break;
+        }
+        line_num += 1;
+        state.lines_read += 1;
+        state.bytes_read += bytes;
+
+        // 不正なバイト列は U+FFFD に置き換えて出力を続ける
+        let line = String::from_utf8_lossy(trim_newline(&buf));
+        invalid_utf8 |= matches!(line, Cow::Owned(_));
+        let line = line.into_owned();
+
+        // 範囲外の行も行番号を進めるために処理はするが、出力はしない
+        let in_range = config.line_range.is_none_or(|(start, _)| line_num >= start);
+
+        // GNU cat と同じく、空行の圧縮は行番号を振る前に行う
+        // 圧縮で残った空行は -m number では1つの番号を持ち、number-and-nonblank では番号を持たない
+        if line.is_empty() {
+            blank_run += 1;
+            if config.squeeze_limit.is_some_and(|limit| blank_run > limit) {
+                continue;
             }
+        } else {
+            blank_run = 0;
+        }
+        let is_blank = line.is_empty();
+        let line = decorate(config, line);
+        let output = match config.print_mode {
+            PrintMode::Normal => line,
             PrintMode::Number => {
-                let header = format!("{:>6}", i);
-                println!("{}\t{}", header, line);
-                i += 1;
+                let header = format!("{:>6}", state.line_number);
+                state.line_number += 1;
+                format!("{}\t{}", header, line)
             }
             PrintMode::NumberAndNonblank => {
-                if line.is_empty() {
-                    println!("");
+                if is_blank {
+                    line
                 } else {
-                    let header = format!("{:>6}", i);
-                    println!("{}\t{}", header, line);
-                    i += 1;
+                    let header = format!("{:>6}", state.line_number);
+                    state.line_number += 1;
+                    format!("{}\t{}", header, line)
                 }
             }
+        };
+        if in_range {
+            emit(output)?;
         }
     }
 
-    Ok(())
+    Ok(invalid_utf8)
+
+}
+
+// 行末の "\n" または "\r\n" を取り除く
+fn trim_newline(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+// -T, -E の指定に応じて行の内容を変換する
+fn decorate(config: &Config, line: String) -> String {
+    let line = if config.show_tabs {
+        line.replace('\t', "^I")
+    } else {
+        line
+    };
+    if config.show_ends {
+        line + "$"
+    } else {
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::{cat_file, parse_line_range, render, CatState, Config, PrintMode};
+
+    fn config(print_mode: PrintMode) -> Config {
+        Config::with_mode(print_mode)
+    }
+
+    fn cat(config: &Config, text: &str) -> String {
+        let mut out = Vec::new();
+        cat_file(config, "-", Cursor::new(text.to_string()), false, &mut CatState::new(), &mut out)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_parse_line_range() {
+        assert_eq!(parse_line_range("10:20").unwrap(), (10, 20));
+        assert_eq!(parse_line_range("3:3").unwrap(), (3, 3));
+
+        // START > END, 0 始まり、形式違いはエラー
+        let res = parse_line_range("20:10");
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().to_string(), "illegal line range: \"20:10\"");
+        assert!(parse_line_range("0:10").is_err());
+        assert!(parse_line_range("10").is_err());
+        assert!(parse_line_range("a:b").is_err());
+    }
+
+    #[test]
+    fn test_squeeze_blank() {
+        let mut cfg = config(PrintMode::Normal);
+        cfg.squeeze_limit = Some(1);
+        assert_eq!(cat(&cfg, "a\n\n\n\nb\n"), "a\n\nb\n");
 
+        // 残った空行は行番号のカウントに含める
+        cfg.print_mode = PrintMode::Number;
+        assert_eq!(
+            cat(&cfg, "a\n\n\n\nb\n"),
+            "     1\ta\n     2\t\n     3\tb\n"
+        );
+
+        // 空行には番号を振らないので、圧縮しても番号は飛ばない
+        cfg.print_mode = PrintMode::NumberAndNonblank;
+        assert_eq!(cat(&cfg, "a\n\n\n\nb\n"), "     1\ta\n\n     2\tb\n");
+    }
+
+    #[test]
+    fn test_show_ends_and_tabs() {
+        let text = "a\tb\n\n\tc\t\n";
+
+        let mut cfg = config(PrintMode::Normal);
+        cfg.show_tabs = true;
+        assert_eq!(cat(&cfg, text), "a^Ib\n\n^Ic^I\n");
+
+        cfg.show_tabs = false;
+        cfg.show_ends = true;
+        assert_eq!(cat(&cfg, text), "a\tb$\n$\n\tc\t$\n");
+
+        cfg.show_tabs = true;
+        assert_eq!(cat(&cfg, text), "a^Ib$\n$\n^Ic^I$\n");
+
+        // 行番号の区切りのタブは変換しない
+        cfg.print_mode = PrintMode::Number;
+        assert_eq!(
+            cat(&cfg, text),
+            "     1\ta^Ib$\n     2\t$\n     3\t^Ic^I$\n"
+        );
+        cfg.print_mode = PrintMode::NumberAndNonblank;
+        assert_eq!(
+            cat(&cfg, text),
+            "     1\ta^Ib$\n$\n     2\t^Ic^I$\n"
+        );
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let cfg = config(PrintMode::Normal);
+        let mut out = Vec::new();
+        let input = Cursor::new(b"ok\r\nbad \xff byte\nlast".to_vec());
+        assert!(cat_file(&cfg, "-", input, false, &mut CatState::new(), &mut out).is_ok());
+        assert_eq!(String::from_utf8(out).unwrap(), "ok\nbad \u{FFFD} byte\nlast\n");
+    }
+
+    #[test]
+    fn test_render() {
+        let text = "a\n\nb\n";
+        let lines = render(Cursor::new(text), PrintMode::Number).unwrap();
+        assert_eq!(lines, vec!["     1\ta", "     2\t", "     3\tb"]);
+
+        let lines = render(Cursor::new(text), PrintMode::NumberAndNonblank).unwrap();
+        assert_eq!(lines, vec!["     1\ta", "", "     2\tb"]);
+
+        let lines = render(Cursor::new(""), PrintMode::Normal).unwrap();
+        assert!(lines.is_empty());
+    }
+
+    #[test]
+    fn test_reverse() {
+        let mut cfg = config(PrintMode::Number);
+        cfg.reverse = true;
+        let mut state = CatState::new();
+        let mut out = Vec::new();
+        cat_file(&cfg, "-", Cursor::new("a\nb\nc"), false, &mut state, &mut out).unwrap();
+        // 行番号は元の順番でついたまま逆順になる
+        assert_eq!(String::from_utf8(out).unwrap(), "     3\tc\n     2\tb\n     1\ta\n");
+
+        // ファイルごとに逆順にし、行番号は次のファイルに続く
+        let mut out = Vec::new();
+        cat_file(&cfg, "-", Cursor::new("d\ne\n"), true, &mut state, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "     5\te\n     4\td\n");
+    }
+
+    #[test]
+    fn test_stats_counts() {
+        let cfg = config(PrintMode::Number);
+        let mut state = CatState::new();
+        let mut out = Vec::new();
+        cat_file(&cfg, "-", Cursor::new("a\r\n\nbc"), false, &mut state, &mut out).unwrap();
+        assert_eq!((state.lines_read, state.bytes_read), (3, 6));
+
+        // ファイルをまたいで足し合わせる
+        cat_file(&cfg, "-", Cursor::new("xyz\n"), true, &mut state, &mut out).unwrap();
+        assert_eq!((state.lines_read, state.bytes_read), (4, 10));
+    }
+
+    #[test]
+    fn test_line_range() {
+        let text: String = (1..=30).map(|n| format!("line{}\n", n)).collect();
+
+        let mut cfg = config(PrintMode::Normal);
+        cfg.line_range = Some((10, 20));
+        let expected: String = (10..=20).map(|n| format!("line{}\n", n)).collect();
+        assert_eq!(cat(&cfg, &text), expected);
+
+        // 行番号は元のファイルでの行番号を表示する
+        cfg.print_mode = PrintMode::Number;
+        let expected: String = (10..=20).map(|n| format!("{:>6}\tline{}\n", n, n)).collect();
+        assert_eq!(cat(&cfg, &text), expected);
+    }
+
+    #[test]
+    fn test_line_range_per_file() {
+        // 範囲も行番号もファイルごとに数える
+        let mut cfg = config(PrintMode::Number);
+        cfg.line_range = Some((2, 3));
+        let mut state = CatState::new();
+        let mut out = Vec::new();
+        cat_file(&cfg, "-", Cursor::new("a1\na2\na3\na4\n"), false, &mut state, &mut out).unwrap();
+        cat_file(&cfg, "-", Cursor::new("b1\nb2\nb3\nb4\n"), true, &mut state, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "     2\ta2\n     3\ta3\n     2\tb2\n     3\tb3\n"
+        );
+        // 範囲の最後の行 (3行目) までしか読まない
+        assert_eq!((state.lines_read, state.bytes_read), (6, 18));
+    }
 }
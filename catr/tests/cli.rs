@@ -11,6 +11,9 @@ const EMPTY: &str = "tests/inputs/empty.txt";
 const FOX: &str = "tests/inputs/fox.txt";
 const SPIDERS: &str = "tests/inputs/spiders.txt";
 const BUSTLE: &str = "tests/inputs/the-bustle.txt";
+const BLANKS: &str = "tests/inputs/blanks.txt";
+const TABS: &str = "tests/inputs/tabs.txt";
+const INVALID_UTF8: &str = "tests/inputs/invalid-utf8.txt";
 
 // --------------------------------------------------
 #[test]
@@ -198,3 +201,166 @@ fn all_n() -> TestResult {
 fn all_b() -> TestResult {
     run(&[FOX, SPIDERS, BUSTLE, "-m", "number-and-nonblank"], "tests/expected/all.b.out")
 }
+
+// --------------------------------------------------
+#[test]
+fn blanks_squeeze_limit() -> TestResult {
+    run(&["--squeeze-limit", "2", BLANKS], "tests/expected/blanks.txt.squeeze2.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn headers() -> TestResult {
+    run(&["--headers", FOX, SPIDERS], "tests/expected/fox_spiders.headers.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn headers_n() -> TestResult {
+    run(
+        &["--headers", "-m", "number", FOX, SPIDERS],
+        "tests/expected/fox_spiders.headers.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn blanks_squeeze() -> TestResult {
+    run(&["-s", BLANKS], "tests/expected/blanks.txt.s.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn tabs_show_ends_and_tabs() -> TestResult {
+    run(&["-E", "-T", TABS], "tests/expected/tabs.txt.ET.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn invalid_utf8() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([INVALID_UTF8, FOX])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("caf\u{FFFD}\nplain\nThe quick brown fox"))
+        .stderr(format!("{}: invalid utf-8\n", INVALID_UTF8));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn stdin_between_files_n() -> TestResult {
+    let expected = fs::read_to_string("tests/expected/fox_stdin_spiders.n.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["-m", "number", FOX, "-", SPIDERS])
+        .write_stdin("from\nstdin\n")
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn squeeze_then_number() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-m", "number", "-s"])
+        .write_stdin("a\n\n\n\nb\n")
+        .assert()
+        .success()
+        .stdout("     1\ta\n     2\t\n     3\tb\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn squeeze_then_number_nonblank() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-m", "number-and-nonblank", "-s"])
+        .write_stdin("a\n\n\n\nb\n")
+        .assert()
+        .success()
+        .stdout("     1\ta\n\n     2\tb\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn stats() -> TestResult {
+    let expected = fs::read_to_string("tests/expected/the-bustle.txt.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["--stats", BUSTLE])
+        .assert()
+        .success()
+        .stdout(expected)
+        .stderr("9 lines, 193 bytes\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn stats_all_n() -> TestResult {
+    // 出力に付けた行番号は数えず、読んだ分だけを足し合わせる
+    Command::cargo_bin(PRG)?
+        .args(["--stats", "-m", "number", FOX, SPIDERS, BUSTLE])
+        .assert()
+        .success()
+        .stderr("13 lines, 283 bytes\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn stats_line_range() -> TestResult {
+    // 範囲の最後の行より後は読まないので数えない
+    Command::cargo_bin(PRG)?
+        .args(["--stats", "--line-range", "2:3", "-"])
+        .write_stdin("a\nbb\nccc\ndddd\n")
+        .assert()
+        .success()
+        .stdout("bb\nccc\n")
+        .stderr("3 lines, 9 bytes\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn spiders_reverse() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--reverse", SPIDERS])
+        .assert()
+        .success()
+        .stdout("casually.\nI keep house\nDon't worry, spiders,\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn spiders_reverse_n() -> TestResult {
+    // 行番号は元の行についたまま逆順になる
+    Command::cargo_bin(PRG)?
+        .args(["--reverse", "-m", "number", SPIDERS])
+        .assert()
+        .success()
+        .stdout("     3\tcasually.\n     2\tI keep house\n     1\tDon't worry, spiders,\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn reverse_each_file_with_headers() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--reverse", "--headers", FOX, SPIDERS])
+        .assert()
+        .success()
+        .stdout(
+            "==> tests/inputs/fox.txt <==\n\
+             The quick brown fox jumps over the lazy dog.\n\
+             \n\
+             ==> tests/inputs/spiders.txt <==\n\
+             casually.\n\
+             I keep house\n\
+             Don't worry, spiders,\n",
+        );
+    Ok(())
+}
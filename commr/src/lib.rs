@@ -1,6 +1,6 @@
 use std::{error::Error, fs::File, io::{self, BufRead, BufReader, Lines}};
 
-use clap::{ArgAction, Parser};
+use clap::{ArgAction, Parser, ValueEnum};
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
@@ -44,7 +44,66 @@ pub struct Args {
         long = "output-delimiter",
         default_value = "\t"
     )]
-    delimiter: String
+    delimiter: String,
+
+    /// Print a first line labeling the columns (col1, col2, both)
+    #[arg(long)]
+    header: bool,
+
+    /// How to handle input that is not in sorted order
+    #[arg(
+        long = "check-order",
+        value_name = "MODE",
+        value_enum,
+        default_value = "auto"
+    )]
+    check_order: CheckOrder,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CheckOrder {
+    /// Fail on the first line out of order
+    Strict,
+    /// Warn once and keep going
+    Auto,
+    /// Do not check
+    None,
+}
+
+// 各ファイルの直前の行 (比較に使うキー) を覚えておき、並び順が崩れていないか調べる
+struct OrderChecker {
+    mode: CheckOrder,
+    prev: [Option<String>; 2],
+    warned: bool,
+}
+
+impl OrderChecker {
+    fn new(mode: CheckOrder) -> Self {
+        OrderChecker { mode, prev: [None, None], warned: false }
+    }
+
+    // file は 1 か 2
+    fn check(&mut self, file: usize, key: &str) -> MyResult<()> {
+        if self.mode == CheckOrder::None {
+            return Ok(());
+        }
+
+        let prev = &mut self.prev[file - 1];
+        if prev.as_deref().is_some_and(|prev| key < prev) {
+            let msg = format!("file {} is not in sorted order", file);
+            match self.mode {
+                CheckOrder::Strict => return Err(From::from(msg)),
+                _ if !self.warned => {
+                    eprintln!("{}", msg);
+                    self.warned = true;
+                }
+                _ => (),
+            }
+        }
+        *prev = Some(key.to_string());
+
+        Ok(())
+    }
 }
 
 pub fn get_args() -> MyResult<Args> {
@@ -78,6 +137,7 @@ fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
 
 // Todo:
 // case-sensitiveのやつ
+// 行を1つ使うたびに、そのファイルの並び順を check_order に従って調べる
 fn proc_lines(
     iter1: &mut Lines<Box<dyn BufRead>>,
     iter2: &mut Lines<Box<dyn BufRead>>,
@@ -86,6 +146,8 @@ fn proc_lines(
     let mut vec = Vec::new();
     let mut content1 = iter1.next();
     let mut content2 = iter2.next();
+    let mut order = OrderChecker::new(args.check_order);
+    let key = |line: &str| if args.insensitive { line.to_lowercase() } else { line.to_string() };
 
     loop {
         match (&content1, &content2) {
@@ -94,29 +156,31 @@ fn proc_lines(
             },
             (None, Some(res)) => {
                 let line = res.as_ref().map_err(|e| format!("line処理: {:#?}", e))?;
+                order.check(2, &key(line))?;
                 vec.push((2, line.clone()));
                 content2 = iter2.next()
             },
             (Some(res), None) => {
                 let line = res.as_ref().map_err(|e| format!("line処理: {:#?}", e))?;
+                order.check(1, &key(line))?;
                 vec.push((1, line.clone()));
                 content1 = iter1.next()
             }
             (Some(res1), Some(res2)) => {
                 let line1 = res1.as_ref().map_err(|e| format!("line処理: {:#?}", e))?;
                 let line2 = res2.as_ref().map_err(|e| format!("line処理: {:#?}", e))?;
-                let (cmp1, cmp2) = if args.insensitive {
-                    (line1.to_lowercase(), line2.to_lowercase())
-                } else {
-                    (line1.to_string(), line2.to_string())
-                };
+                let (cmp1, cmp2) = (key(line1), key(line2));
                 if cmp1 < cmp2 {
+                    order.check(1, &cmp1)?;
                     vec.push((1, line1.clone()));
                     content1 = iter1.next()
                 } else if cmp1 > cmp2 {
+                    order.check(2, &cmp2)?;
                     vec.push((2, line2.clone()));
                     content2 = iter2.next()
                 } else {
+                    order.check(1, &cmp1)?;
+                    order.check(2, &cmp2)?;
                     vec.push((3, line1.clone()));
                     content1 = iter1.next();
                     content2 = iter2.next()
@@ -129,7 +193,16 @@ fn proc_lines(
 }
 
 // 表示しないカラムがある場合は、左詰めにしないといけない
-fn print_result(res: &Vec<(usize, String)>, args: &Args) {
+// --header のときは、表示するカラムの見出しを同じ区切りで並べて先頭に出す
+fn print_result(res: &[(usize, String)], args: &Args) {
+    if args.header {
+        let labels: Vec<&str> = [(args.show_col1, "col1"), (args.show_col2, "col2"), (args.show_col3, "both")]
+            .into_iter()
+            .filter_map(|(show, label)| if show { Some(label) } else { None })
+            .collect();
+        println!("{}", labels.join(&args.delimiter));
+    }
+
     res.iter().for_each(|(i, line)| {
         match i {
             1 if args.show_col1 => {
@@ -8,6 +8,7 @@ const EMPTY: &str = "tests/inputs/empty.txt";
 const FILE1: &str = "tests/inputs/file1.txt";
 const FILE2: &str = "tests/inputs/file2.txt";
 const BLANK: &str = "tests/inputs/blank.txt";
+const UNSORTED: &str = "tests/inputs/unsorted.txt";
 
 type TestResult = Result<(), Box<dyn std::error::Error>>;
 
@@ -42,7 +43,7 @@ fn dies_bad_file1() -> TestResult {
     let bad = gen_bad_file();
     let expected = format!("{}: .* [(]os error 2[)]", bad);
     Command::cargo_bin(PRG)?
-        .args(&[&bad, FILE1])
+        .args([&bad, FILE1])
         .assert()
         .failure()
         .stderr(predicate::str::is_match(expected)?);
@@ -55,7 +56,7 @@ fn dies_bad_file2() -> TestResult {
     let bad = gen_bad_file();
     let expected = format!("{}: .* [(]os error 2[)]", bad);
     Command::cargo_bin(PRG)?
-        .args(&[FILE1, &bad])
+        .args([FILE1, &bad])
         .assert()
         .failure()
         .stderr(predicate::str::is_match(expected)?);
@@ -67,7 +68,7 @@ fn dies_bad_file2() -> TestResult {
 fn dies_both_stdin() -> TestResult {
     let expected = "Both input files can't be STDIN (\"-\")";
     Command::cargo_bin(PRG)?
-        .args(&["-", "-"])
+        .args(["-", "-"])
         .assert()
         .failure()
         .stderr(predicate::str::contains(expected));
@@ -340,6 +341,138 @@ fn blank_file1() -> TestResult {
     run(&[BLANK, FILE1], "tests/expected/blank_file1.out")
 }
 
+// --------------------------------------------------
+fn run_check_order(mode: &str, stderr: &str) -> TestResult {
+    let expected = fs::read_to_string("tests/expected/unsorted_file2.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["--check-order", mode, UNSORTED, FILE2])
+        .assert()
+        .success()
+        .stdout(expected)
+        .stderr(stderr.to_string());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn check_order_auto_by_default() -> TestResult {
+    let expected = fs::read_to_string("tests/expected/unsorted_file2.out")?;
+    Command::cargo_bin(PRG)?
+        .args([UNSORTED, FILE2])
+        .assert()
+        .success()
+        .stdout(expected)
+        .stderr("file 1 is not in sorted order\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn check_order_auto() -> TestResult {
+    run_check_order("auto", "file 1 is not in sorted order\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn check_order_auto_warns_once() -> TestResult {
+    // 両方のファイルが崩れていても警告は1回だけ
+    Command::cargo_bin(PRG)?
+        .args(["--check-order", "auto", UNSORTED, UNSORTED])
+        .assert()
+        .success()
+        .stderr("file 1 is not in sorted order\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn check_order_none() -> TestResult {
+    run_check_order("none", "")
+}
+
+// --------------------------------------------------
+#[test]
+fn check_order_strict() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--check-order", "strict", FILE2, UNSORTED])
+        .assert()
+        .failure()
+        .code(1)
+        .stderr("file 2 is not in sorted order\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn check_order_strict_sorted() -> TestResult {
+    run(
+        &["--check-order", "strict", FILE1, FILE2],
+        "tests/expected/file1_file2.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_check_order() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--check-order", "sometimes", FILE1, FILE2])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid value 'sometimes'"));
+    Ok(())
+}
+
+// --------------------------------------------------
+fn run_header(args: &[&str], header: &str, expected_file: &str) -> TestResult {
+    let expected = format!("{}\n{}", header, fs::read_to_string(expected_file)?);
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn file1_file2_header() -> TestResult {
+    run_header(
+        &["--header", FILE1, FILE2],
+        "col1\tcol2\tboth",
+        "tests/expected/file1_file2.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn file1_file2_1_header() -> TestResult {
+    run_header(
+        &["--header", "-1", FILE1, FILE2],
+        "col2\tboth",
+        "tests/expected/file1_file2.1.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn file1_file2_13_delim_header() -> TestResult {
+    run_header(
+        &["--header", FILE1, FILE2, "-13", "-d", ":"],
+        "col2",
+        "tests/expected/file1_file2.13.delim.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn file1_file2_delim_header() -> TestResult {
+    run_header(
+        &["--header", FILE1, FILE2, "-d", ":"],
+        "col1:col2:both",
+        "tests/expected/file1_file2.delim.out",
+    )
+}
+
 //// --------------------------------------------------
 //#[test]
 //fn file1_blanks() -> TestResult {
@@ -1,12 +1,17 @@
 use crate::Extract::*;
 use std::{error::Error, fs::File, io::{self, BufRead, BufReader}, num::NonZeroUsize, ops::Range};
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use csv::{ReaderBuilder, StringRecord};
 use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
+// --pad で空のセルを埋める位置の上限
+// 埋める位置はすべて並べて持つので、-f 1-4000000000 のような範囲でメモリを使い果たさないようにする
+const MAX_PAD_FIELDS: usize = 65536;
+
 #[derive(Debug, Parser)]
 #[command(version, about)]
 struct Args {
@@ -19,20 +24,42 @@ struct Args {
     #[arg(short, long, value_name = "DELILMITER", default_value = "\t")]
     delimiter: String,
 
+    /// Field delimiter given as a regex (fields only, e.g. '\s*,\s*')
+    #[arg(long, value_name = "REGEX", conflicts_with = "delimiter")]
+    regex_delimiter: Option<String>,
+
+    /// String used to join selected fields [default: the --delimiter]
+    #[arg(long, value_name = "STRING")]
+    output_delimiter: Option<String>,
+
+    /// With --regex-delimiter, join adjacent selected fields with the original separator text
+    #[arg(long)]
+    keep_spacing: bool,
+
     /// Selected fields
     #[arg(
         short,
         long,
         value_name = "FIELDS",
+        allow_hyphen_values = true,
         conflicts_with_all(["bytes", "chars"])
     )]
     fields: Option<String>,
 
+    /// Print the first line of each input unmodified and cut only the following lines
+    #[arg(long)]
+    keep_header: bool,
+
+    /// Print the number of fields in each record instead of extracting
+    #[arg(long, conflicts_with_all(["fields", "bytes", "chars"]))]
+    count_fields: bool,
+
     /// Selected bytes
     #[arg(
         short,
         long,
         value_name = "BYTES",
+        allow_hyphen_values = true,
         conflicts_with_all(["fields", "chars"])
     )]
     bytes: Option<String>,
@@ -42,12 +69,34 @@ struct Args {
         short,
         long,
         value_name = "CHARS",
+        allow_hyphen_values = true,
         conflicts_with_all(["fields", "bytes"])
     )]
     chars: Option<String>,
+
+    /// With --chars, count grapheme clusters instead of Unicode scalar values
+    #[arg(long)]
+    graphemes: bool,
+
+    /// Line terminator written after each output record
+    #[arg(long = "output-eol", value_name = "EOL", value_enum, default_value = "lf")]
+    output_eol: Eol,
+
+    /// Pad fields beyond the end of a record with empty cells (finite ranges only)
+    #[arg(long)]
+    pad: bool,
+
+    /// Skip records with no selected fields instead of printing blank lines
+    #[arg(long, overrides_with = "keep_empty")]
+    suppress_empty: bool,
+
+    /// Print records with no selected fields as blank lines (default)
+    #[arg(long, overrides_with = "suppress_empty")]
+    keep_empty: bool,
 }
 
 impl Args {
+    #[allow(clippy::wrong_self_convention)]
     fn to_config(self) -> MyResult<Config> {
         let delim_bytes = self.delimiter.as_bytes();
         if delim_bytes.len() != 1 {
@@ -55,26 +104,93 @@ impl Args {
         }
         let delimiter: u8 = *delim_bytes.first().unwrap();
 
+        let regex_delimiter = self
+            .regex_delimiter
+            .map(|re| {
+                Regex::new(&re)
+                    .map_err(|_| format!("--regex-delimiter \"{}\" is not a valid regex", re))
+            })
+            .transpose()?;
+
+        // どのオプションの指定が間違っていたのかわかるように、オプション名を前につける
+        let parse = |option: &str, list: Option<String>| {
+            list.map(parse_pos)
+                .transpose()
+                .map_err(|e| format!("{}: {}", option, e))
+        };
+
+        // --fields は --bytes, --chars と衝突するため clap の requires では検出できないのでここで調べる
+        if self.pad && self.fields.is_none() {
+            return Err(From::from("--pad can only be used with --fields"));
+        }
+        if self.graphemes && self.chars.is_none() {
+            return Err(From::from("--graphemes can only be used with --chars"));
+        }
+        if self.output_delimiter.is_some() && self.fields.is_none() {
+            return Err(From::from("--output-delimiter can only be used with --fields"));
+        }
+        if self.keep_spacing && regex_delimiter.is_none() {
+            return Err(From::from("--keep-spacing can only be used with --regex-delimiter"));
+        }
+
         let extract =
-            if let Some(fields) = self.fields.map(parse_pos).transpose()? {
+            if let Some(fields) = parse("--fields", self.fields)? {
                 Fields(fields)
-            } else if let Some(bytes) = self.bytes.map(parse_pos).transpose()? {
+            } else if let Some(bytes) = parse("--bytes", self.bytes)? {
                 Bytes(bytes)
-            } else if let Some(chars) = self.chars.map(parse_pos).transpose()? {
-                Chars(chars)
+            } else if let Some(chars) = parse("--chars", self.chars)? {
+                if self.graphemes { Graphemes(chars) } else { Chars(chars) }
+            } else if self.count_fields {
+                FieldCount
             } else {
                 return Err(From::from("Must have --fields, --bytes, or --chars"))
             };
 
+        if let (true, Fields(ranges)) = (self.pad, &extract) {
+            if let Some(range) = ranges.iter().find(|r| r.end != usize::MAX && r.end > MAX_PAD_FIELDS) {
+                return Err(From::from(format!(
+                    "--pad: field {} is too large to pad (at most {})",
+                    range.end, MAX_PAD_FIELDS
+                )));
+            }
+        }
+
 
         Ok(Config {
             files: self.files,
             delimiter,
+            output_delimiter: self.output_delimiter.unwrap_or_else(|| (delimiter as char).to_string()),
+            keep_spacing: self.keep_spacing,
+            regex_delimiter,
             extract,
+            keep_header: self.keep_header,
+            suppress_empty: self.suppress_empty,
+            pad: self.pad,
+            eol: self.output_eol,
         })
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Eol {
+    /// "\n"
+    Lf,
+    /// "\r\n"
+    Crlf,
+    /// No terminator (records are concatenated)
+    None,
+}
+
+impl Eol {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Eol::Lf => "\n",
+            Eol::Crlf => "\r\n",
+            Eol::None => "",
+        }
+    }
+}
+
 type PositionList = Vec<Range<usize>>;
 
 #[derive(Debug)]
@@ -82,13 +198,32 @@ enum Extract {
     Fields(PositionList),
     Bytes(PositionList),
     Chars(PositionList),
+    // --chars --graphemes のとき。位置は書記素クラスタ単位で数える
+    Graphemes(PositionList),
+    // --count-fields のとき。フィールドは取り出さずに、各レコードのフィールドの数を出す
+    FieldCount,
 }
 
 #[derive(Debug)]
 pub struct Config {
     files: Option<Vec<String>>,
     delimiter: u8,
+    // 選択したフィールドを連結する文字列
+    output_delimiter: String,
+    // regex_delimiter で分割したとき、元の行で隣り合っていたフィールドどうしは元の区切りの文字列で連結するか
+    // 指定しなければ、元の空白などは残らず output_delimiter で連結する
+    keep_spacing: bool,
+    // 指定された場合は csv reader を使わずにこの正規表現で各行（ヘッダ行も含む）を分割する
+    regex_delimiter: Option<Regex>,
     extract: Extract,
+    // 各入力の最初の行をヘッダとして切り出さずにそのまま出すか
+    keep_header: bool,
+    // 選択したフィールドがひとつもないレコードを空行として出さずに飛ばすか
+    suppress_empty: bool,
+    // レコードの列数が足りない場合に空のセルで埋めて、出力の列数をそろえるか
+    pad: bool,
+    // 各レコードの後に出力する改行
+    eol: Eol,
 }
 
 enum Input {
@@ -98,27 +233,37 @@ enum Input {
 
 fn parse_index(input: &str) -> Result<usize, String> {
     let value_error = || format!("illegal list value: \"{}\"", input);
-    input
-        .starts_with('+')
-        .then(|| Err(value_error()))
-        .unwrap_or_else(|| {
-            input
-                .parse::<NonZeroUsize>()
-                .map(|n| usize::from(n) - 1)
-                .map_err(|_| value_error())
-        })
+    if input.starts_with('+') {
+        Err(value_error())
+    } else {
+        input
+            .parse::<NonZeroUsize>()
+            .map(|n| usize::from(n) - 1)
+            .map_err(|_| value_error())
+    }
 }
 
+// "N-" は N から最後まで (終端を usize::MAX とする)、"-N" は最初から N まで
 fn parse_pos(range: String) -> MyResult<PositionList> {
-    let range_re = Regex::new(r"^(\d+)-(\d+)$").unwrap();
+    let range_re = Regex::new(r"^(\d*)-(\d*)$").unwrap();
     range
         .split(',')
-        .into_iter()
         .map(|val| {
             parse_index(val).map(|n| n..n+1).or_else(|e| {
                 range_re.captures(val).ok_or(e).and_then(|captures| {
-                    let n1 = parse_index(&captures[1])?;
-                    let n2 = parse_index(&captures[2])?;
+                    let (start, end) = (&captures[1], &captures[2]);
+                    if start.is_empty() && end.is_empty() {
+                        return Err(format!("illegal list value: \"{}\"", val));
+                    }
+                    if end.is_empty() {
+                        return Ok(parse_index(start)?..usize::MAX);
+                    }
+                    if start.is_empty() {
+                        return Ok(0..parse_index(end)?+1);
+                    }
+
+                    let n1 = parse_index(start)?;
+                    let n2 = parse_index(end)?;
                     if n1 >= n2 {
                         return Err(format!(
                             "First number in range ({}) \
@@ -143,24 +288,56 @@ fn open(input: Input) -> MyResult<Box<dyn BufRead>> {
     }
 }
 
-fn extract_fields(record: &StringRecord, field_pos: &[Range<usize>]) -> Vec<String> {
-    // 指定された range に含まれる field のリストを返す。見つからなかった場合は None を返す
-    let subfield = |record: &StringRecord, range: Range<usize>| -> Option<Vec<String>> {
-        let found: Vec<String> = record.iter()
-            .enumerate()
-            .filter_map(|(i, field)| if range.contains(&i) { Some(field.to_string()) } else { None })
-            .collect();
-
-        if found.is_empty() { None } else { Some(found) }
-    };
-
+// len 個のフィールドからなるレコードについて、選択されたフィールドの位置を出力する順に返す
+// pad のときは、終端のある範囲についてはレコードにない位置も返す (終端は to_config で MAX_PAD_FIELDS までに制限する)
+// 終端が開いた範囲 (usize::MAX) はレコードの最後までしか埋めない
+fn selected_indices(len: usize, field_pos: &[Range<usize>], pad: bool) -> Vec<usize> {
     field_pos.iter()
-        .cloned()
-        .filter_map(|range| subfield(&record, range))
-        .flatten()
+        .flat_map(|range| {
+            let end = if pad && range.end != usize::MAX { range.end } else { range.end.min(len) };
+            range.start..end.max(range.start)
+        })
         .collect()
 }
 
+// pad のときは、レコードにない位置は空文字列として返す
+fn extract_fields(record: &StringRecord, field_pos: &[Range<usize>], pad: bool) -> Vec<String> {
+    selected_indices(record.len(), field_pos, pad)
+        .into_iter()
+        .map(|i| record.get(i).unwrap_or("").to_string())
+        .collect()
+}
+
+// 正規表現で分割した行から選択したフィールドを取り出して連結する
+// 元の行で隣り合っていたフィールドどうしは、その間にあった区切りの文字列をそのまま使い、
+// それ以外は delim で連結する。選択したフィールドがなければ None を返す
+fn extract_fields_keep_spacing(
+    line: &str,
+    re: &Regex,
+    field_pos: &[Range<usize>],
+    pad: bool,
+    delim: &str,
+) -> Option<String> {
+    let fields: Vec<&str> = re.split(line).collect();
+    // seps[i] は fields[i] と fields[i + 1] の間の区切り
+    let seps: Vec<&str> = re.find_iter(line).map(|m| m.as_str()).collect();
+
+    let indices = selected_indices(fields.len(), field_pos, pad);
+    if indices.is_empty() {
+        return None;
+    }
+
+    let mut joined = String::new();
+    for (n, &i) in indices.iter().enumerate() {
+        if n > 0 {
+            let prev = indices[n - 1];
+            joined.push_str(if i == prev + 1 { seps.get(prev).copied().unwrap_or(delim) } else { delim });
+        }
+        joined.push_str(fields.get(i).copied().unwrap_or(""));
+    }
+    Some(joined)
+}
+
 fn extract_chars(line: &str, char_pos: &[Range<usize>]) -> String {
     let substring = |s: &str, start: usize, end: usize| -> String {
         s.chars()
@@ -175,13 +352,30 @@ fn extract_chars(line: &str, char_pos: &[Range<usize>]) -> String {
         .join("")
 }
 
+// 結合文字や国旗の絵文字のように複数の char からなるものを分割しないように、書記素クラスタ単位で取り出す
+fn extract_graphemes(line: &str, grapheme_pos: &[Range<usize>]) -> String {
+    let substring = |s: &str, start: usize, end: usize| -> String {
+        s.graphemes(true)
+            .enumerate()
+            .filter_map(|(i, g)| if start <= i && i < end { Some(g) } else { None })
+            .collect()
+    };
+
+    grapheme_pos.iter()
+        .map(|range| substring(line, range.start, range.end))
+        .collect::<Vec<_>>()
+        .join("")
+}
+
 fn extract_bytes(line: &str, byte_pos: &[Range<usize>]) -> String {
     let subbytes = |s: &str, range: Range<usize>| -> String {
         let bytes = s.as_bytes();
+        // 終端が開いた範囲 (usize::MAX) は行末までに切り詰める
+        let range = range.start..range.end.min(bytes.len());
         String::from_utf8_lossy(bytes.get(range).unwrap_or(b"")).to_string()
     };
 
-    byte_pos.into_iter()
+    byte_pos.iter()
         .cloned()
         .map(|range| subbytes(line, range))
         .collect::<Vec<_>>()
@@ -192,28 +386,82 @@ pub fn get_config() -> MyResult<Config> {
     Args::parse().to_config()
 }
 
-fn print(config: &Config, buf_reader: Box<dyn BufRead>) -> MyResult<()> {
+// レコードを1つずつ f に渡す
+// regex_delimiter があれば各行をその正規表現で、なければ csv reader で delimiter によって分割する
+// csv reader にはヘッダ行を特別扱いさせず、最初の行もほかと同じレコードとして渡す
+// (ヘッダを切り出さずに残すのは --keep-header で、reader に渡す前に print で読んでおく)
+fn for_each_record(
+    config: &Config,
+    buf_reader: Box<dyn BufRead>,
+    mut f: impl FnMut(&StringRecord),
+) -> MyResult<()> {
+    if let Some(re) = &config.regex_delimiter {
+        for line in buf_reader.lines() {
+            f(&StringRecord::from(re.split(&line?).collect::<Vec<_>>()));
+        }
+        return Ok(());
+    }
+
+    // レコードごとにフィールドの数が違っても読めるようにする
+    let mut reader = ReaderBuilder::new()
+        .delimiter(config.delimiter)
+        .flexible(true)
+        .has_headers(false)
+        .from_reader(buf_reader);
+
+    for record in reader.records() {
+        f(&record?);
+    }
+    Ok(())
+}
+
+fn print(config: &Config, mut buf_reader: Box<dyn BufRead>) -> MyResult<()> {
+    let eol = config.eol.as_str();
+
+    // --keep-header のときは、どのモードでも最初の行を切り出さずにそのまま出してから残りを処理する
+    // 改行だけは他のレコードにそろえて eol に置き換える
+    if config.keep_header {
+        let mut header = String::new();
+        if buf_reader.read_line(&mut header)? > 0 {
+            let header = header.strip_suffix('\n').map_or(header.as_str(), |h| h.strip_suffix('\r').unwrap_or(h));
+            print!("{}{}", header, eol);
+        }
+    }
+
     match &config.extract {
         Fields(ranges) => {
-            let mut reader = ReaderBuilder::new()
-                .delimiter(config.delimiter)
-                .from_reader(buf_reader);
-
-            let header = reader.headers()?;
-            let delim = (config.delimiter as char).to_string();
-            println!("{}", extract_fields(&header, ranges).join(&delim));
-            for record in reader.records() {
-                let record = record?;
-                println!(
-                    "{}", extract_fields(&record, ranges).join(&delim)
-                )
+            let delim = &config.output_delimiter;
+            let print_fields = |fields: Vec<String>| {
+                if !(config.suppress_empty && fields.is_empty()) {
+                    print!("{}{}", fields.join(delim), eol)
+                }
+            };
+
+            if let Some(re) = &config.regex_delimiter {
+                if config.keep_spacing {
+                    for line in buf_reader.lines() {
+                        match extract_fields_keep_spacing(&line?, re, ranges, config.pad, delim) {
+                            Some(joined) => print!("{}{}", joined, eol),
+                            None if !config.suppress_empty => print!("{}", eol),
+                            None => {},
+                        }
+                    }
+                    return Ok(());
+                }
             }
-            Ok(())
+
+            // --output-delimiter がなければ --delimiter (regex_delimiter のときはデフォルトのタブ) で連結する
+            for_each_record(config, buf_reader, |record| {
+                print_fields(extract_fields(record, ranges, config.pad))
+            })
+        },
+        FieldCount => {
+            for_each_record(config, buf_reader, |record| print!("{}{}", record.len(), eol))
         },
         Bytes(ranges) => {
             for line in buf_reader.lines() {
                 let line = line?;
-                println!("{}", extract_bytes(line.as_str(), ranges))
+                print!("{}{}", extract_bytes(line.as_str(), ranges), eol)
             }
 
             Ok(())
@@ -221,7 +469,15 @@ fn print(config: &Config, buf_reader: Box<dyn BufRead>) -> MyResult<()> {
         Chars(ranges) => {
             for line in buf_reader.lines() {
                 let line = line?;
-                println!("{}", extract_chars(line.as_str(), ranges))
+                print!("{}{}", extract_chars(line.as_str(), ranges), eol)
+            }
+
+            Ok(())
+        },
+        Graphemes(ranges) => {
+            for line in buf_reader.lines() {
+                let line = line?;
+                print!("{}{}", extract_graphemes(line.as_str(), ranges), eol)
             }
 
             Ok(())
@@ -258,12 +514,15 @@ pub fn run(config: Config) -> MyResult<()> {
 
 // ------------------------------------------------------------
 #[cfg(test)]
+#[allow(clippy::single_range_in_vec_init)]
 mod unit_tests {
     use csv::StringRecord;
 
-    use crate::extract_fields;
+    use regex::Regex;
 
-    use super::{extract_chars, extract_bytes, parse_pos};
+    use crate::{extract_fields, extract_fields_keep_spacing};
+
+    use super::{extract_chars, extract_bytes, extract_graphemes, parse_pos};
 
     #[test]
     fn test_parse_pos() {
@@ -346,9 +605,6 @@ mod unit_tests {
         let res = parse_pos("1,".to_string());
         assert!(res.is_err());
 
-        let res = parse_pos("1-".to_string());
-        assert!(res.is_err());
-
         let res = parse_pos("1-1-1".to_string());
         assert!(res.is_err());
 
@@ -402,6 +658,33 @@ mod unit_tests {
         let res = parse_pos("15,19-20".to_string());
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), vec![14..15, 18..20]);
+
+        // Open-ended ranges
+        let res = parse_pos("2-".to_string());
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), vec![1..usize::MAX]);
+
+        let res = parse_pos("-3".to_string());
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), vec![0..3]);
+
+        let res = parse_pos("1,4-,6".to_string());
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), vec![0..1, 3..usize::MAX, 5..6]);
+
+        let res = parse_pos("0-".to_string());
+        assert!(res.is_err());
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            r#"illegal list value: "0""#
+        );
+
+        let res = parse_pos("-0".to_string());
+        assert!(res.is_err());
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            r#"illegal list value: "0""#
+        );
     }
     
 
@@ -413,6 +696,22 @@ mod unit_tests {
         assert_eq!(extract_chars("Émile", &[0..3]), "Émi".to_string());
         assert_eq!(extract_chars("Émile", &[2..3, 1..2]), "im".to_string());
         assert_eq!(extract_chars("Émile", &[0..1, 1..2, 6..7]), "Ém".to_string());
+        assert_eq!(extract_chars("Émile", &[2..usize::MAX]), "ile".to_string());
+    }
+
+    #[test]
+    fn test_extract_graphemes() {
+        // "e" + 結合アキュート (U+0301) と、2つの regional indicator からなる日本の国旗
+        let line = "Ce\u{301}\u{1F1EF}\u{1F1F5}!";
+        assert_eq!(extract_graphemes("", &[0..1]), "".to_string());
+        assert_eq!(extract_graphemes(line, &[1..2]), "e\u{301}".to_string());
+        assert_eq!(extract_graphemes(line, &[2..3]), "\u{1F1EF}\u{1F1F5}".to_string());
+        assert_eq!(extract_graphemes(line, &[3..4, 0..1]), "!C".to_string());
+        assert_eq!(extract_graphemes(line, &[1..usize::MAX]), "e\u{301}\u{1F1EF}\u{1F1F5}!".to_string());
+
+        // chars() で数えるとクラスタの途中で切れてしまう
+        assert_eq!(extract_chars(line, &[1..2]), "e".to_string());
+        assert_eq!(extract_chars(line, &[3..4]), "\u{1F1EF}".to_string());
     }
 
     #[test]
@@ -423,17 +722,47 @@ mod unit_tests {
         assert_eq!(extract_bytes("ábc", &[0..4]), "ábc".to_string());
         assert_eq!(extract_bytes("ábc", &[3..4, 2..3]), "cb".to_string());
         assert_eq!(extract_bytes("ábc", &[0..2, 5..6]), "á".to_string());
+        assert_eq!(extract_bytes("ábc", &[2..usize::MAX]), "bc".to_string());
+        assert_eq!(extract_bytes("ábc", &[5..usize::MAX]), "".to_string());
     }
 
     #[test]
     fn test_extract_fields() {
         let rec = StringRecord::from(vec!["Captain", "Sham", "12345"]);
-        assert_eq!(extract_fields(&rec, &[0..1]), &["Captain"]);
-        assert_eq!(extract_fields(&rec, &[1..2]), &["Sham"]);
-        assert_eq!(extract_fields(&rec, &[0..1, 2..3]), &["Captain", "12345"]);
-        assert_eq!(extract_fields(&rec, &[0..1, 3..4]), &["Captain"]);
-        assert_eq!(extract_fields(&rec, &[1..2, 0..1]), &["Sham", "Captain"]);
-        assert_eq!(extract_fields(&rec, &[100..150]), vec!["dummy"; 0]);
-        assert_eq!(extract_fields(&rec, &[0..100]), &["Captain", "Sham", "12345"])
+        assert_eq!(extract_fields(&rec, &[0..1], false), &["Captain"]);
+        assert_eq!(extract_fields(&rec, &[1..2], false), &["Sham"]);
+        assert_eq!(extract_fields(&rec, &[0..1, 2..3], false), &["Captain", "12345"]);
+        assert_eq!(extract_fields(&rec, &[0..1, 3..4], false), &["Captain"]);
+        assert_eq!(extract_fields(&rec, &[1..2, 0..1], false), &["Sham", "Captain"]);
+        assert_eq!(extract_fields(&rec, &[100..150], false), vec!["dummy"; 0]);
+        assert_eq!(extract_fields(&rec, &[0..100], false), &["Captain", "Sham", "12345"]);
+        assert_eq!(extract_fields(&rec, &[1..usize::MAX], false), &["Sham", "12345"])
+    }
+
+    #[test]
+    fn test_extract_fields_pad() {
+        let rec = StringRecord::from(vec!["Captain", "Sham", "12345"]);
+        // -f 1-5 --pad
+        assert_eq!(extract_fields(&rec, &[0..5], true), &["Captain", "Sham", "12345", "", ""]);
+        assert_eq!(extract_fields(&rec, &[3..4, 0..1], true), &["", "Captain"]);
+        assert_eq!(extract_fields(&rec, &[100..101], true), &[""]);
+        // 終端が開いた範囲は埋めない
+        assert_eq!(extract_fields(&rec, &[1..usize::MAX], true), &["Sham", "12345"]);
+        assert_eq!(extract_fields(&rec, &[5..usize::MAX], true), vec!["dummy"; 0]);
+    }
+
+    #[test]
+    fn test_extract_fields_keep_spacing() {
+        let re = Regex::new(" +").unwrap();
+        let line = "alice   30   Tokyo";
+        // 隣り合うフィールドは元の空白で連結する
+        assert_eq!(extract_fields_keep_spacing(line, &re, &[0..2], false, "\t"), Some("alice   30".to_string()));
+        assert_eq!(extract_fields_keep_spacing(line, &re, &[1..usize::MAX], false, "\t"), Some("30   Tokyo".to_string()));
+        // 隣り合わないフィールドや、順番を入れ替えたフィールドは delim で連結する
+        assert_eq!(extract_fields_keep_spacing(line, &re, &[0..1, 2..3], false, ","), Some("alice,Tokyo".to_string()));
+        assert_eq!(extract_fields_keep_spacing(line, &re, &[1..2, 0..1], false, ","), Some("30,alice".to_string()));
+        // レコードにない位置
+        assert_eq!(extract_fields_keep_spacing(line, &re, &[3..4], false, ","), None);
+        assert_eq!(extract_fields_keep_spacing(line, &re, &[2..5], true, ","), Some("Tokyo,,".to_string()));
     }
 }
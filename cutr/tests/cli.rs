@@ -9,6 +9,10 @@ const PRG: &str = "cutr";
 const CSV: &str = "tests/inputs/movies1.csv";
 const TSV: &str = "tests/inputs/movies1.tsv";
 const BOOKS: &str = "tests/inputs/books.tsv";
+const RAGGED: &str = "tests/inputs/ragged.csv";
+const MESSY: &str = "tests/inputs/messy.csv";
+const GRAPHEMES: &str = "tests/inputs/graphemes.txt";
+const ALIGNED: &str = "tests/inputs/aligned.txt";
 
 // --------------------------------------------------
 fn random_string() -> String {
@@ -35,7 +39,7 @@ fn skips_bad_file() -> TestResult {
     let bad = gen_bad_file();
     let expected = format!("{}: .* [(]os error 2[)]", bad);
     Command::cargo_bin(PRG)?
-        .args(&["-f", "1", CSV, &bad, TSV])
+        .args(["-f", "1", CSV, &bad, TSV])
         .assert()
         .success()
         .stderr(predicate::str::is_match(expected)?);
@@ -64,7 +68,7 @@ fn dies_bad_digit_field() -> TestResult {
     let bad = random_string();
     dies(
         &[CSV, "-f", &bad],
-        &format!("illegal list value: \"{}\"", &bad),
+        &format!("--fields: illegal list value: \"{}\"", &bad),
     )
 }
 
@@ -74,7 +78,7 @@ fn dies_bad_digit_bytes() -> TestResult {
     let bad = random_string();
     dies(
         &[CSV, "-b", &bad],
-        &format!("illegal list value: \"{}\"", &bad),
+        &format!("--bytes: illegal list value: \"{}\"", &bad),
     )
 }
 
@@ -84,10 +88,31 @@ fn dies_bad_digit_chars() -> TestResult {
     let bad = random_string();
     dies(
         &[CSV, "-c", &bad],
-        &format!("illegal list value: \"{}\"", &bad),
+        &format!("--chars: illegal list value: \"{}\"", &bad),
     )
 }
 
+// --------------------------------------------------
+#[test]
+fn dies_zero_field() -> TestResult {
+    dies(&[CSV, "-f", "0"], "--fields: illegal list value: \"0\"")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_range_bytes() -> TestResult {
+    dies(
+        &[CSV, "-b", "3-2"],
+        "--bytes: First number in range (3) must be lower than second number (2)",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_range_chars() -> TestResult {
+    dies(&[CSV, "-c", "1,+2"], "--chars: illegal list value: \"+2\"")
+}
+
 // --------------------------------------------------
 #[test]
 fn dies_empty_delimiter() -> TestResult {
@@ -110,7 +135,7 @@ fn dies_bad_delimiter() -> TestResult {
 #[test]
 fn dies_chars_bytes_fields() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&[CSV, "-c", "1", "-f", "1", "-b", "1"])
+        .args([CSV, "-c", "1", "-f", "1", "-b", "1"])
         .assert()
         .failure();
     Ok(())
@@ -120,7 +145,7 @@ fn dies_chars_bytes_fields() -> TestResult {
 #[test]
 fn dies_bytes_fields() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&[CSV, "-f", "1", "-b", "1"])
+        .args([CSV, "-f", "1", "-b", "1"])
         .assert()
         .failure();
     Ok(())
@@ -130,7 +155,7 @@ fn dies_bytes_fields() -> TestResult {
 #[test]
 fn dies_chars_fields() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&[CSV, "-c", "1", "-f", "1"])
+        .args([CSV, "-c", "1", "-f", "1"])
         .assert()
         .failure();
     Ok(())
@@ -140,7 +165,7 @@ fn dies_chars_fields() -> TestResult {
 #[test]
 fn dies_chars_bytes() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&[CSV, "-c", "1", "-b", "1"])
+        .args([CSV, "-c", "1", "-b", "1"])
         .assert()
         .failure();
     Ok(())
@@ -337,3 +362,311 @@ fn tsv_c1_8() -> TestResult {
 fn repeated_value() -> TestResult {
     run(&[BOOKS, "-c", "1,1"], "tests/expected/books.c1,1.out")
 }
+
+// --------------------------------------------------
+#[test]
+fn tsv_f2_open() -> TestResult {
+    run(&[TSV, "-f", "2-"], "tests/expected/movies1.tsv.f2-3.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_f_open_2() -> TestResult {
+    run(&[TSV, "-f", "-2"], "tests/expected/movies1.tsv.f1-2.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_b_open_2() -> TestResult {
+    run(&[TSV, "-b", "-2"], "tests/expected/movies1.tsv.b1-2.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_c2_open() -> TestResult {
+    run(&[TSV, "-c", "2-"], "tests/expected/movies1.tsv.c2-.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn ragged_f3_keep_empty() -> TestResult {
+    run(
+        &[RAGGED, "-d", ",", "-f", "3"],
+        "tests/expected/ragged.csv.f3.dcomma.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn ragged_f3_suppress_empty() -> TestResult {
+    run(
+        &[RAGGED, "-d", ",", "-f", "3", "--suppress-empty"],
+        "tests/expected/ragged.csv.f3.dcomma.suppress.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn ragged_f3_suppress_then_keep_empty() -> TestResult {
+    run(
+        &[RAGGED, "-d", ",", "-f", "3", "--suppress-empty", "--keep-empty"],
+        "tests/expected/ragged.csv.f3.dcomma.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn messy_f1_3_regex_delimiter() -> TestResult {
+    run(
+        &[MESSY, "--regex-delimiter", r"\s*,\s*", "-f", "1,3"],
+        "tests/expected/messy.csv.f1,3.regex.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_regex_delimiter() -> TestResult {
+    dies(
+        &[MESSY, "--regex-delimiter", "(", "-f", "1"],
+        r#"--regex-delimiter "(" is not a valid regex"#,
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_regex_delimiter_with_delimiter() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([MESSY, "-d", ",", "--regex-delimiter", ",", "-f", "1"])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn ragged_f1_4_pad() -> TestResult {
+    run(
+        &[RAGGED, "-d", ",", "-f", "1-4", "--pad"],
+        "tests/expected/ragged.csv.f1-4.dcomma.pad.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_pad_without_fields() -> TestResult {
+    dies(&[CSV, "-c", "1", "--pad"], "--pad can only be used with --fields")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_pad_huge_range() -> TestResult {
+    // 埋める位置をすべて確保しようとしてメモリを使い果たさずに、エラーにする
+    dies(
+        &[RAGGED, "-d", ",", "-f", "1-4000000000", "--pad"],
+        "--pad: field 4000000000 is too large to pad (at most 65536)",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn pad_up_to_max_and_open_range() -> TestResult {
+    // 上限ちょうどまでは埋める。終端が開いた範囲はレコードの最後までしか埋めないので、上限の対象にしない
+    Command::cargo_bin(PRG)?
+        .args(["-d", ",", "-f", "1-65536,2-", "--pad"])
+        .write_stdin("a,b\n")
+        .assert()
+        .success()
+        .stdout(format!("a,b{},b\n", ",".repeat(65534)));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn graphemes_c2_3() -> TestResult {
+    run(
+        &[GRAPHEMES, "-c", "2-3", "--graphemes"],
+        "tests/expected/graphemes.txt.c2-3.graphemes.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_graphemes_without_chars() -> TestResult {
+    dies(
+        &[GRAPHEMES, "-b", "1", "--graphemes"],
+        "--graphemes can only be used with --chars",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn output_eol_crlf() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([BOOKS, "-f", "1", "--output-eol", "crlf"])
+        .assert()
+        .success()
+        .stdout("Author\r\nÉmile Zola\r\nSamuel Beckett\r\nJules Verne\r\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn output_eol_crlf_chars() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([BOOKS, "-c", "1", "--output-eol", "crlf"])
+        .assert()
+        .success()
+        .stdout("A\r\nÉ\r\nS\r\nJ\r\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn output_eol_none() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([BOOKS, "-c", "1", "--output-eol", "none"])
+        .assert()
+        .success()
+        .stdout("AÉSJ");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_output_eol() -> TestResult {
+    dies(&[BOOKS, "-f", "1", "--output-eol", "cr"], "invalid value 'cr'")
+}
+
+// --------------------------------------------------
+#[test]
+fn aligned_f1_2_keep_spacing() -> TestResult {
+    run(
+        &[ALIGNED, "--regex-delimiter", " +", "-f", "1-2", "--keep-spacing"],
+        "tests/expected/aligned.txt.f1-2.keep.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn aligned_f1_2_regex_default_delimiter() -> TestResult {
+    // 元の空白は残らず、デフォルトではタブで連結する
+    Command::cargo_bin(PRG)?
+        .args([ALIGNED, "--regex-delimiter", " +", "-f", "1-2"])
+        .assert()
+        .success()
+        .stdout("NAME\tAGE\nalice\t30\nbob\t4\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn aligned_f1_3_output_delimiter() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([ALIGNED, "--regex-delimiter", " +", "-f", "1,3", "--output-delimiter", " "])
+        .assert()
+        .success()
+        .stdout("NAME CITY\nalice Tokyo\nbob Osaka\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn aligned_f1_3_keep_spacing_not_adjacent() -> TestResult {
+    // 隣り合わないフィールドは --output-delimiter で連結する
+    Command::cargo_bin(PRG)?
+        .args([ALIGNED, "--regex-delimiter", " +", "-f", "1,2-3", "--keep-spacing", "--output-delimiter", ","])
+        .assert()
+        .success()
+        .stdout("NAME    AGE  CITY\nalice   30   Tokyo\nbob     4    Osaka\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn output_delimiter_csv() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([BOOKS, "-f", "1,2", "--output-delimiter", ";"])
+        .assert()
+        .success()
+        .stdout("Author;Year\nÉmile Zola;1865\nSamuel Beckett;1952\nJules Verne;1870\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_keep_spacing_without_regex_delimiter() -> TestResult {
+    dies(
+        &[ALIGNED, "-f", "1", "--keep-spacing"],
+        "--keep-spacing can only be used with --regex-delimiter",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_output_delimiter_without_fields() -> TestResult {
+    dies(
+        &[ALIGNED, "-c", "1", "--output-delimiter", ","],
+        "--output-delimiter can only be used with --fields",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn ragged_count_fields() -> TestResult {
+    run(
+        &[RAGGED, "-d", ",", "--count-fields"],
+        "tests/expected/ragged.csv.count.dcomma.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn aligned_count_fields_regex_delimiter() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([ALIGNED, "--regex-delimiter", " +", "--count-fields"])
+        .assert()
+        .success()
+        .stdout("3\n3\n3\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_count_fields_and_fields() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([RAGGED, "-f", "1", "--count-fields"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_f2_keep_header() -> TestResult {
+    run(
+        &[CSV, "-d", ",", "-f", "2", "--keep-header"],
+        "tests/expected/movies1.csv.f2.dcomma.header.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_b1_2_keep_header() -> TestResult {
+    run(
+        &[TSV, "-b", "1-2", "--keep-header"],
+        "tests/expected/movies1.tsv.b1-2.header.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn keep_header_each_file() -> TestResult {
+    // ファイルごとに最初の行をそのまま出す。ヘッダの改行も --output-eol にそろえる
+    Command::cargo_bin(PRG)?
+        .args([RAGGED, RAGGED, "-d", ",", "-f", "3", "--keep-header", "--output-eol", "crlf"])
+        .assert()
+        .success()
+        .stdout("a,b,c\r\n\r\nz\r\n\r\na,b,c\r\n\r\nz\r\n\r\n");
+    Ok(())
+}
@@ -2,9 +2,10 @@
 use clap::{Parser, ValueEnum};
 use regex::Regex;
 
-use walkdir::{DirEntry, WalkDir};
+use ignore::{WalkBuilder, WalkState};
+use walkdir::WalkDir;
 use EntryType::*;
-use std::error::Error;
+use std::{error::Error, fs::{self, FileType}, io::{self, BufWriter, Write}, path::{Path, PathBuf}, sync::Mutex};
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
@@ -15,6 +16,52 @@ enum EntryType {
     Link,
 }
 
+// --size の条件
+// 比較の前にファイルサイズを unit 単位に切り上げる (find と同じ)
+#[derive(Debug, Eq, PartialEq, Clone)]
+enum SizeSpec {
+    Greater { n: u64, unit: u64 },
+    Less { n: u64, unit: u64 },
+    Exact { n: u64, unit: u64 },
+}
+
+impl SizeSpec {
+    fn matches(&self, len: u64) -> bool {
+        match *self {
+            SizeSpec::Greater { n, unit } => len.div_ceil(unit) > n,
+            SizeSpec::Less { n, unit } => len.div_ceil(unit) < n,
+            SizeSpec::Exact { n, unit } => len.div_ceil(unit) == n,
+        }
+    }
+}
+
+// [+-]N[ckMG] の形式をパースする (単位なしはバイト)
+fn parse_size(spec: &str) -> MyResult<SizeSpec> {
+    let invalid = || From::from(format!("invalid size spec \"{}\"", spec));
+
+    let (sign, rest) = match spec.chars().next() {
+        Some(c @ ('+' | '-')) => (Some(c), &spec[1..]),
+        _ => (None, spec),
+    };
+    let (num, unit) = match rest.chars().last() {
+        Some('c') => (&rest[..rest.len() - 1], 1),
+        Some('k') => (&rest[..rest.len() - 1], 1 << 10),
+        Some('M') => (&rest[..rest.len() - 1], 1 << 20),
+        Some('G') => (&rest[..rest.len() - 1], 1 << 30),
+        _ => (rest, 1),
+    };
+    if num.is_empty() || !num.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let n = num.parse().map_err(|_| invalid())?;
+
+    Ok(match sign {
+        Some('+') => SizeSpec::Greater { n, unit },
+        Some(_) => SizeSpec::Less { n, unit },
+        None => SizeSpec::Exact { n, unit },
+    })
+}
+
 #[derive(Debug, Parser)]
 #[command(version, about)]
 pub struct Config {
@@ -43,6 +90,43 @@ pub struct Config {
     )]
     #[clap(value_enum)]
     entry_types: Option<Vec<EntryType>>,
+
+    /// File extension to filter result, case-insensitive (can be given multiple times)
+    #[arg(long = "ext", value_name = "EXT")]
+    exts: Option<Vec<String>>,
+
+    /// Descend at most this many levels (0 is the starting path itself)
+    #[arg(long, value_name = "DEPTH")]
+    max_depth: Option<usize>,
+
+    /// Ignore entries shallower than this level
+    #[arg(long, value_name = "DEPTH")]
+    min_depth: Option<usize>,
+
+    /// File size to filter result (e.g. +10k, -1M, 100c)
+    #[arg(
+        long,
+        value_name = "SIZE",
+        allow_hyphen_values = true,
+        value_parser = |s: &str| parse_size(s).map_err(|e| e.to_string())
+    )]
+    size: Option<SizeSpec>,
+
+    /// Separate results with NUL instead of newline
+    #[arg(short = '0', long)]
+    print0: bool,
+
+    /// Only match empty files and directories
+    #[arg(long)]
+    empty: bool,
+
+    /// Walk the tree with N threads (results are sorted by path when N > 1)
+    #[arg(long, value_name = "N", default_value = "1", value_parser = clap::value_parser!(u16).range(1..))]
+    threads: u16,
+
+    /// Print results relative to each starting path (the starting path itself is ".")
+    #[arg(long)]
+    relative: bool,
 }
 
 pub fn get_config() -> MyResult<Config> {
@@ -51,28 +135,133 @@ pub fn get_config() -> MyResult<Config> {
 
 
 pub fn run(config: Config) -> MyResult<()> {
+    let mut out = BufWriter::new(io::stdout());
+    find(&config, &mut out)?;
+    out.flush()?;
+
+    Ok(())
+}
+
+// 条件に合うエントリを out に書き出す
+// threads が 1 ならたどった順にそのまま書き出し、2 以上なら並列にたどって集めたものを並べ替えてから書き出す
+fn find(config: &Config, out: &mut dyn Write) -> MyResult<()> {
+    // xargs -0 に渡せるように、-0 のときは改行の代わりに NUL で区切る
+    let terminator = if config.print0 { '\0' } else { '\n' };
+
+    // --relative のときは、たどり始めたパスからの相対パスにする
+    let shown = |root: &str, path: &Path| -> PathBuf {
+        if config.relative { relative_path(Path::new(root), path).to_path_buf() } else { path.to_path_buf() }
+    };
+
+    if config.threads > 1 {
+        for (root, path) in find_parallel(config) {
+            write!(out, "{}{}", shown(root, &path).display(), terminator)?;
+        }
+        return Ok(());
+    }
+
+    for path in &config.paths {
+        let mut walker = WalkDir::new(path);
+        if let Some(depth) = config.max_depth {
+            walker = walker.max_depth(depth);
+        }
+        if let Some(depth) = config.min_depth {
+            walker = walker.min_depth(depth);
+        }
+
+        for entry in walker {
+            match entry {
+                Err(e) => eprintln!("{}", e),
+                Ok(entry) => match is_match(config, entry.path(), entry.file_type()) {
+                    Err(e) => eprintln!("{}: {}", entry.path().display(), e),
+                    Ok(true) => write!(out, "{}{}", shown(path, entry.path()).display(), terminator)?,
+                    Ok(false) => {}
+                },
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// config.threads 個のスレッドで並列にたどり、条件に合うエントリのパスを並べ替えて返す
+// --relative で使えるように、どのパスからたどったものかも一緒に返す
+// find と同じく symlink はたどらず、隠しファイルや .gitignore も特別扱いしない
+fn find_parallel(config: &Config) -> Vec<(&str, PathBuf)> {
+    let found = Mutex::new(Vec::new());
+
+    // パスが重なっていてもどこからたどったかがわかるように、パスごとにたどる
+    for root in &config.paths {
+        let mut builder = WalkBuilder::new(root);
+        builder
+            .standard_filters(false)
+            .max_depth(config.max_depth)
+            .threads(config.threads as usize);
+        walk_parallel(config, root, builder, &found);
+    }
+
+    // たどる順番は実行のたびに変わるので並べ替える
+    let mut found = found.into_inner().unwrap();
+    found.sort_by(|(_, a), (_, b)| a.cmp(b));
+    found
+}
+
+fn walk_parallel<'a>(config: &Config, root: &'a str, builder: WalkBuilder, found: &Mutex<Vec<(&'a str, PathBuf)>>) {
+    builder.build_parallel().run(|| {
+        Box::new(move |entry| {
+            match entry {
+                Err(e) => eprintln!("{}", e),
+                Ok(entry) if entry.depth() < config.min_depth.unwrap_or(0) => {}
+                Ok(entry) => {
+                    let Some(file_type) = entry.file_type() else {
+                        return WalkState::Continue;
+                    };
+                    match is_match(config, entry.path(), file_type) {
+                        Err(e) => eprintln!("{}: {}", entry.path().display(), e),
+                        Ok(true) => found.lock().unwrap().push((root, entry.into_path())),
+                        Ok(false) => {}
+                    }
+                }
+            }
+            WalkState::Continue
+        })
+    });
+}
 
-    let match_by_type = |entry: & DirEntry| {
+// root からの相対パスを返す。root 自身なら "."
+fn relative_path<'a>(root: &Path, path: &'a Path) -> &'a Path {
+    match path.strip_prefix(root) {
+        Ok(rel) if rel.as_os_str().is_empty() => Path::new("."),
+        Ok(rel) => rel,
+        Err(_) => path,
+    }
+}
+
+// path (種類は file_type) がすべての条件に合うか
+// file_type は symlink をたどらずに調べたもの
+fn is_match(config: &Config, path: &Path, file_type: FileType) -> MyResult<bool> {
+    let match_by_type = || {
         match &config.entry_types {
             None => true,
             Some(entry_types) => {
                 entry_types.iter()
                     .any(|entry_type| {
                         match entry_type {
-                            File => entry.file_type().is_file(),
-                            Dir => entry.file_type().is_dir(),
-                            Link => entry.file_type().is_symlink(),
+                            File => file_type.is_file(),
+                            Dir => file_type.is_dir(),
+                            Link => file_type.is_symlink(),
                         }
                     })
             }
         }
     };
 
-    let match_by_name = |entry: & DirEntry| {
+    // 開始パスのようにファイル名がない場合 ("." など) はパス全体を名前とする (walkdir と同じ)
+    let match_by_name = || {
         match &config.names {
             None => true,
             Some(names) => {
-                let entry_name = entry.file_name().to_string_lossy();
+                let entry_name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy();
                 names.iter()
                     .any(|name| {
                         name.is_match(&entry_name)
@@ -81,19 +270,220 @@ pub fn run(config: Config) -> MyResult<()> {
         }
     };
 
-    for path in config.paths {
-        for entry in WalkDir::new(path) {
-            match entry {
-                Err(e) => eprintln!("{}", e),
-                Ok(entry) => {
-                    if match_by_type(&entry) && match_by_name(&entry) {
-                        println!("{}", entry.path().display())
-                    }
+    // 拡張子は大文字小文字を区別しない。先頭の "." はあってもなくてもよい
+    let match_by_ext = || {
+        match &config.exts {
+            None => true,
+            Some(exts) => {
+                path.extension().is_some_and(|entry_ext| {
+                    let entry_ext = entry_ext.to_string_lossy();
+                    exts.iter()
+                        .any(|ext| ext.trim_start_matches('.').eq_ignore_ascii_case(&entry_ext))
+                })
+            }
+        }
+    };
+
+    // サイズはファイルにのみ意味があるので、ファイル以外は --size 指定時にはマッチさせない
+    let match_by_size = || -> MyResult<bool> {
+        match &config.size {
+            None => Ok(true),
+            Some(_) if !file_type.is_file() => Ok(false),
+            Some(size) => Ok(size.matches(fs::symlink_metadata(path)?.len())),
+        }
+    };
+
+    // 中身を読めないディレクトリは空でないものとして扱う
+    // symlink はたどらないので、リンク先が空でもマッチさせない
+    let match_by_empty = || {
+        if !config.empty {
+            return true;
+        }
+        if file_type.is_dir() {
+            match fs::read_dir(path) {
+                Ok(mut dir) => dir.next().is_none(),
+                Err(e) => {
+                    eprintln!("{}: {}", path.display(), e);
+                    false
                 }
             }
+        } else {
+            file_type.is_file()
+                && fs::symlink_metadata(path)
+                    .map(|metadata| metadata.len() == 0)
+                    .unwrap_or(false)
         }
-    }
+    };
 
-    Ok(())
+    if !(match_by_type() && match_by_name() && match_by_ext() && match_by_empty()) {
+        return Ok(false);
+    }
+    match_by_size()
 }
 
+#[cfg(test)]
+mod tests {
+    use super::{find, parse_size, relative_path, Config, SizeSpec};
+    use std::path::Path;
+    use clap::Parser;
+
+    // 引数を与えて find を実行し、出力を行ごとに並べ替えて返す
+    fn find_sorted(args: &[&str]) -> Vec<String> {
+        let config = Config::try_parse_from(["findr"].iter().chain(args)).unwrap();
+        let mut out = Vec::new();
+        find(&config, &mut out).unwrap();
+        let mut lines: Vec<String> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| line.replace('\\', "/"))
+            .collect();
+        lines.sort();
+        lines
+    }
+
+    #[test]
+    fn test_find_type_and_name() {
+        assert_eq!(
+            find_sorted(&["tests/inputs", "-t", "file", "-n", "\\.csv$"]),
+            vec!["tests/inputs/a/b/b.csv", "tests/inputs/g.csv"]
+        );
+        assert_eq!(
+            find_sorted(&["tests/inputs/d", "-t", "link"]),
+            vec!["tests/inputs/d/b.csv"]
+        );
+        assert_eq!(
+            find_sorted(&["tests/inputs/a", "-t", "dir"]),
+            vec!["tests/inputs/a", "tests/inputs/a/b", "tests/inputs/a/b/c"]
+        );
+    }
+
+    #[test]
+    fn test_find_depth() {
+        assert_eq!(
+            find_sorted(&["tests/inputs/a", "--min-depth", "1", "--max-depth", "1"]),
+            vec!["tests/inputs/a/a.txt", "tests/inputs/a/b"]
+        );
+        assert_eq!(
+            find_sorted(&["tests/inputs/a", "-n", "txt", "--max-depth", "0"]),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn test_find_size() {
+        assert_eq!(
+            find_sorted(&["tests/sizes", "--size", "+1k"]),
+            vec!["tests/sizes/big.txt", "tests/sizes/two_k.txt"]
+        );
+        assert_eq!(
+            find_sorted(&["tests/sizes", "-n", "tiny", "--size", "-2k"]),
+            vec!["tests/sizes/tiny.txt"]
+        );
+    }
+
+    #[test]
+    fn test_find_ext() {
+        assert_eq!(
+            find_sorted(&["tests/inputs", "--ext", "txt"]),
+            vec!["tests/inputs/a/a.txt", "tests/inputs/d/d.txt", "tests/inputs/f/f.txt"]
+        );
+        // 大文字小文字は区別せず、複数指定はいずれかにマッチすればよい
+        assert_eq!(
+            find_sorted(&["tests/inputs", "--ext", "CSV", "--ext", ".mp3", "-n", "^[bc]"]),
+            vec!["tests/inputs/a/b/b.csv", "tests/inputs/a/b/c/c.mp3", "tests/inputs/d/b.csv"]
+        );
+    }
+
+    #[test]
+    fn test_find_print0() {
+        let config = Config::try_parse_from(["findr", "tests/inputs/f", "-0"]).unwrap();
+        let mut out = Vec::new();
+        find(&config, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap().replace('\\', "/");
+        let mut entries: Vec<&str> = out.split_terminator('\0').collect();
+        entries.sort();
+        assert_eq!(entries, vec!["tests/inputs/f", "tests/inputs/f/f.txt"]);
+    }
+
+    #[test]
+    fn test_relative_path() {
+        let root = Path::new("tests/inputs");
+        assert_eq!(relative_path(root, Path::new("tests/inputs/a/a.txt")), Path::new("a/a.txt"));
+        // たどり始めたパス自身は "."
+        assert_eq!(relative_path(root, Path::new("tests/inputs")), Path::new("."));
+        assert_eq!(relative_path(Path::new("tests/inputs/"), Path::new("tests/inputs")), Path::new("."));
+        assert_eq!(relative_path(Path::new("."), Path::new("./a")), Path::new("a"));
+    }
+
+    #[test]
+    fn test_find_relative_overlapping_paths() {
+        // パスが重なっていても、それぞれたどり始めたパスからの相対パスになる
+        for threads in ["1", "4"] {
+            assert_eq!(
+                find_sorted(&["tests/inputs/a", "tests/inputs/a/b", "-t", "file", "--relative", "--threads", threads]),
+                ["a.txt", "b.csv", "b/b.csv", "b/c/c.mp3", "c/c.mp3"],
+            );
+        }
+    }
+
+    #[test]
+    fn test_find_threads() {
+        // 並列にたどっても、順番以外は1スレッドのときと同じ結果になる
+        for args in [
+            vec!["tests/inputs"],
+            vec!["tests/inputs/a", "tests/inputs/d", "-t", "file", "link"],
+            vec!["tests/inputs", "-n", "\\.csv$", "--ext", "csv"],
+            vec!["tests/inputs", "--min-depth", "2", "--max-depth", "2"],
+            vec!["tests/sizes", "--size", "+1k"],
+        ] {
+            let mut threaded = args.clone();
+            threaded.extend(["--threads", "4"]);
+            assert_eq!(find_sorted(&threaded), find_sorted(&args), "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn test_find_threads_sorted() {
+        let config = Config::try_parse_from(["findr", "tests/inputs/a", "--threads", "4"]).unwrap();
+        let mut out = Vec::new();
+        find(&config, &mut out).unwrap();
+        let lines: Vec<String> = String::from_utf8(out).unwrap().lines().map(String::from).collect();
+        let mut sorted = lines.clone();
+        sorted.sort();
+        assert_eq!(lines, sorted);
+        assert_eq!(lines.len(), 6);
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("100").unwrap(), SizeSpec::Exact { n: 100, unit: 1 });
+        assert_eq!(parse_size("100c").unwrap(), SizeSpec::Exact { n: 100, unit: 1 });
+        assert_eq!(parse_size("+10k").unwrap(), SizeSpec::Greater { n: 10, unit: 1024 });
+        assert_eq!(parse_size("-1M").unwrap(), SizeSpec::Less { n: 1, unit: 1 << 20 });
+        assert_eq!(parse_size("+2G").unwrap(), SizeSpec::Greater { n: 2, unit: 1 << 30 });
+
+        for bad in ["", "+", "k", "-k", "10x", "1.5k", "++1", "k10"] {
+            let res = parse_size(bad);
+            assert!(res.is_err());
+            assert_eq!(res.unwrap_err().to_string(), format!("invalid size spec \"{}\"", bad));
+        }
+    }
+
+    #[test]
+    fn test_size_matches() {
+        // 単位に切り上げてから比較する
+        let exact = SizeSpec::Exact { n: 2, unit: 1024 };
+        assert!(!exact.matches(1024));
+        assert!(exact.matches(1025));
+        assert!(exact.matches(2048));
+        assert!(!exact.matches(2049));
+
+        let greater = SizeSpec::Greater { n: 10, unit: 1024 };
+        assert!(!greater.matches(10240));
+        assert!(greater.matches(10241));
+
+        let less = SizeSpec::Less { n: 1, unit: 1 };
+        assert!(less.matches(0));
+        assert!(!less.matches(1));
+    }
+}
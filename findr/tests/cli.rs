@@ -39,7 +39,7 @@ fn skips_bad_dir() -> TestResult {
 #[test]
 fn dies_bad_name() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&["--name", "*.csv"])
+        .args(["--name", "*.csv"])
         .assert()
         .failure()
         .stderr(predicate::str::contains("invalid value '*.csv'"));
@@ -51,7 +51,7 @@ fn dies_bad_name() -> TestResult {
 fn dies_bad_type() -> TestResult {
     let expected = "invalid value 'x' for '--type <TYPE>...'";
     Command::cargo_bin(PRG)?
-        .args(&["--type", "x"])
+        .args(["--type", "x"])
         .assert()
         .failure()
         .stderr(predicate::str::contains(expected));
@@ -60,14 +60,14 @@ fn dies_bad_type() -> TestResult {
 
 // --------------------------------------------------
 #[cfg(windows)]
-fn format_file_name(expected_file: &str) -> Cow<str> {
+fn format_file_name(expected_file: &str) -> Cow<'_, str> {
     // Equivalent to: Cow::Owned(format!("{}.windows", expected_file))
     format!("{}.windows", expected_file).into()
 }
 
 // --------------------------------------------------
 #[cfg(not(windows))]
-fn format_file_name(expected_file: &str) -> Cow<str> {
+fn format_file_name(expected_file: &str) -> Cow<'_, str> {
     // Equivalent to: Cow::Borrowed(expected_file)
     expected_file.into()
 }
@@ -281,6 +281,170 @@ fn path_g() -> TestResult {
     run(&["tests/inputs/g.csv"], "tests/expected/path_g.txt")
 }
 
+// --------------------------------------------------
+#[test]
+fn ext_txt() -> TestResult {
+    run(&["tests/inputs", "--ext", "txt"], "tests/expected/ext_txt.txt")
+}
+
+// --------------------------------------------------
+#[test]
+fn ext_txt_tsv_path_a_d() -> TestResult {
+    run(
+        &["tests/inputs/a", "tests/inputs/d", "--ext", "TXT", "--ext", "tsv"],
+        "tests/expected/ext_txt_tsv_path_a_d.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn max_depth_0() -> TestResult {
+    run(
+        &["tests/inputs", "--max-depth", "0"],
+        "tests/expected/max_depth_0.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn max_depth_1() -> TestResult {
+    run(
+        &["tests/inputs", "--max-depth", "1"],
+        "tests/expected/max_depth_1.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn min_depth_2_max_depth_2() -> TestResult {
+    run(
+        &["tests/inputs", "--min-depth", "2", "--max-depth", "2"],
+        "tests/expected/min_depth_2_max_depth_2.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn path1_threads() -> TestResult {
+    run(&["tests/inputs", "--threads", "4"], "tests/expected/path1.txt")
+}
+
+// --------------------------------------------------
+#[test]
+fn min_depth_2_max_depth_2_threads() -> TestResult {
+    run(
+        &["tests/inputs", "--min-depth", "2", "--max-depth", "2", "--threads", "4"],
+        "tests/expected/min_depth_2_max_depth_2.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_zero_threads() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--threads", "0"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid value '0'"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_size() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--size", "10x"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid size spec \"10x\""));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn size_plus_10k() -> TestResult {
+    run(
+        &["tests/sizes", "--size", "+10k"],
+        "tests/expected/size_plus_10k.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn size_minus_2k() -> TestResult {
+    run(
+        &["tests/sizes", "--size", "-2k"],
+        "tests/expected/size_minus_2k.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn size_2k() -> TestResult {
+    run(&["tests/sizes", "--size", "2k"], "tests/expected/size_2k.txt")
+}
+
+// --------------------------------------------------
+#[test]
+fn type_f_size_10c() -> TestResult {
+    run(
+        &["tests/sizes", "-t", "file", "--size", "10c"],
+        "tests/expected/size_10c.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn print0() -> TestResult {
+    let file = format_file_name("tests/expected/path_a_b.txt");
+    let contents = fs::read_to_string(file.as_ref())?;
+    let mut expected: Vec<&str> =
+        contents.split("\n").filter(|s| !s.is_empty()).collect();
+    expected.sort();
+
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["tests/inputs/a/b", "-0"])
+        .assert()
+        .success();
+    let out = cmd.get_output();
+    let stdout = String::from_utf8(out.stdout.clone())?;
+    assert!(!stdout.contains('\n'));
+    assert!(stdout.ends_with('\0'));
+
+    let mut entries: Vec<&str> =
+        stdout.split('\0').filter(|s| !s.is_empty()).collect();
+    entries.sort();
+
+    assert_eq!(entries, expected);
+
+    Ok(())
+}
+
+// --------------------------------------------------
+// 空のディレクトリは git で管理できないのでテスト時に作る
+fn make_empty_dir() -> TestResult {
+    fs::create_dir_all("tests/empty/empty_dir")?;
+    Ok(())
+}
+
+// --------------------------------------------------
+// symlink はたどらないので、空のファイル・ディレクトリへのリンクはマッチしない
+#[test]
+fn empty() -> TestResult {
+    make_empty_dir()?;
+    run(&["tests/empty", "--empty"], "tests/expected/empty.txt")
+}
+
+// --------------------------------------------------
+#[test]
+fn type_f_empty() -> TestResult {
+    make_empty_dir()?;
+    run(
+        &["tests/empty", "--type", "file", "--empty"],
+        "tests/expected/type_f_empty.txt",
+    )
+}
+
 // --------------------------------------------------
 #[test]
 #[cfg(not(windows))]
@@ -295,7 +459,7 @@ fn unreadable_dir() -> TestResult {
     //permissions.set_mode(0o000);
 
     std::process::Command::new("chmod")
-        .args(&["000", dirname])
+        .args(["000", dirname])
         .status()
         .expect("failed");
 
@@ -316,3 +480,27 @@ fn unreadable_dir() -> TestResult {
     assert!(stderr.contains("cant-touch-this: Permission denied"));
     Ok(())
 }
+
+// --------------------------------------------------
+#[test]
+fn relative_path_a() -> TestResult {
+    run(&["tests/inputs/a", "--relative"], "tests/expected/relative_path_a.txt")
+}
+
+// --------------------------------------------------
+#[test]
+fn relative_type_f_path_a_d() -> TestResult {
+    run(
+        &["tests/inputs/a", "tests/inputs/d", "-t", "file", "--relative"],
+        "tests/expected/relative_type_f_path_a_d.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn relative_type_f_path_a_d_threads() -> TestResult {
+    run(
+        &["tests/inputs/a", "tests/inputs/d", "-t", "file", "--relative", "--threads", "4"],
+        "tests/expected/relative_type_f_path_a_d.txt",
+    )
+}
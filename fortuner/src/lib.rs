@@ -6,7 +6,7 @@ use std::{
 };
 
 use clap::Parser;
-use rand::{rngs::StdRng, seq::SliceRandom, RngCore, SeedableRng};
+use rand::{rngs::StdRng, seq::IteratorRandom, RngCore, SeedableRng};
 use regex::{Regex, RegexBuilder};
 use walkdir::WalkDir;
 
@@ -32,6 +32,14 @@ pub struct Args {
     /// Case-insensitive pattern matching
     #[arg(short, long)]
     insensitive: bool,
+
+    /// Minimum length of fortunes to consider
+    #[arg(value_name = "LENGTH", long)]
+    min: Option<usize>,
+
+    /// Maximum length of fortunes to consider
+    #[arg(value_name = "LENGTH", long)]
+    max: Option<usize>,
 }
 
 // ------------------------------------------------------------------------------------------------
@@ -43,6 +51,7 @@ struct Fortune {
 
 // ------------------------------------------------------------------------------------------------
 impl Args {
+    #[allow(clippy::wrong_self_convention)]
     fn to_config(self) -> MyResult<Config> {
         let pattern = self
             .pattern
@@ -56,10 +65,21 @@ impl Args {
 
         let seed = self.seed.map(|s| parse_u64(&s)).transpose()?;
 
+        if let (Some(min), Some(max)) = (self.min, self.max) {
+            if min > max {
+                return Err(From::from(format!(
+                    "--min ({}) must not be greater than --max ({})",
+                    min, max
+                )));
+            }
+        }
+
         Ok(Config {
             pattern,
             sources: self.sources,
             seed,
+            min: self.min,
+            max: self.max,
         })
     }
 }
@@ -93,8 +113,9 @@ fn find_files(paths: &[String]) -> MyResult<Vec<PathBuf>> {
 }
 
 // ------------------------------------------------------------------------------------------------
-fn read_fortunes(paths: &[PathBuf]) -> MyResult<Vec<Fortune>> {
-    let mut fortunes = vec![];
+// ファイルを先頭から読み、"%" で区切られた fortune を見つけるたびに f に渡す
+// すべてをメモリに載せずに済むよう、1 つずつ渡す
+fn for_each_fortune(paths: &[PathBuf], mut f: impl FnMut(Fortune)) -> MyResult<()> {
     let mut buffer = vec![];
 
     for path in paths {
@@ -104,7 +125,7 @@ fn read_fortunes(paths: &[PathBuf]) -> MyResult<Vec<Fortune>> {
         for line in BufReader::new(file).lines().map_while(Result::ok) {
             if line == "%" {
                 if !buffer.is_empty() {
-                    fortunes.push(Fortune {
+                    f(Fortune {
                         source: basename.clone(),
                         text: buffer.join("\n"),
                     });
@@ -116,25 +137,113 @@ fn read_fortunes(paths: &[PathBuf]) -> MyResult<Vec<Fortune>> {
         }
     }
 
+    Ok(())
+}
+
+// ------------------------------------------------------------------------------------------------
+fn read_fortunes(paths: &[PathBuf]) -> MyResult<Vec<Fortune>> {
+    let mut fortunes = vec![];
+    for_each_fortune(paths, |fortune| fortunes.push(fortune))?;
     Ok(fortunes)
 }
 
 // ------------------------------------------------------------------------------------------------
-fn pick_fortune(fortunes: &[Fortune], seed: Option<u64>) -> Option<String> {
-    let mut rng: Box<dyn RngCore> = match seed {
+// 長さ (バイト数) が [min, max] に収まるか
+fn in_length(fortune: &Fortune, min: Option<usize>, max: Option<usize>) -> bool {
+    let len = fortune.text.len();
+    min.is_none_or(|min| len >= min) && max.is_none_or(|max| len <= max)
+}
+
+// ------------------------------------------------------------------------------------------------
+// 長さ (バイト数) が [min, max] に収まるものだけ残す
+fn filter_by_length(
+    fortunes: Vec<Fortune>,
+    min: Option<usize>,
+    max: Option<usize>,
+) -> Vec<Fortune> {
+    fortunes
+        .into_iter()
+        .filter(|f| in_length(f, min, max))
+        .collect()
+}
+
+// ------------------------------------------------------------------------------------------------
+fn new_rng(seed: Option<u64>) -> Box<dyn RngCore> {
+    match seed {
         Some(val) => Box::new(StdRng::seed_from_u64(val)),
         _ => Box::new(rand::thread_rng()),
-    };
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// すべて読み込んでから選ぶ方法。pick_fortune_streaming が同じ seed で同じものを選ぶことをテストで確かめる
+#[cfg(test)]
+fn pick_fortune(fortunes: &[Fortune], seed: Option<u64>) -> Option<String> {
+    use rand::seq::SliceRandom;
 
+    let mut rng = new_rng(seed);
     fortunes.choose(&mut rng).map(|f| f.text.to_string())
 }
 
+// ------------------------------------------------------------------------------------------------
+// ファイルを 2 回読んで選ぶので、大きなファイルでも fortune をすべてメモリに載せない
+// 1 回目で候補の数を数え、pick_fortune (slice の choose) と同じ乱数の使い方で何番目を出すかを決める
+// そのため seed が同じなら、すべて読み込んでから選んだときと同じものを選ぶ
+// (1 回で読み切る reservoir sampling では seed ごとに選ぶものが変わってしまうので、2 回読む方を選んでいる)
+// 長さが [min, max] に収まらないものは候補にしない
+fn pick_fortune_streaming(
+    paths: &[PathBuf],
+    seed: Option<u64>,
+    min: Option<usize>,
+    max: Option<usize>,
+) -> MyResult<Option<String>> {
+    let mut count = 0;
+    for_each_fortune(paths, |f| {
+        if in_length(&f, min, max) {
+            count += 1;
+        }
+    })?;
+
+    let mut rng = new_rng(seed);
+    let Some(index) = (0..count).choose(&mut rng) else {
+        return Ok(None);
+    };
+
+    nth_fortune(paths, index, min, max)
+}
+
+// 長さが [min, max] に収まるもののうち index 番目を取り出す
+// 数えた後にファイルが短くなって index 番目がなければ、最後の候補を返す
+fn nth_fortune(
+    paths: &[PathBuf],
+    index: usize,
+    min: Option<usize>,
+    max: Option<usize>,
+) -> MyResult<Option<String>> {
+    let mut seen = 0;
+    let mut picked = None;
+    let mut last = None;
+    for_each_fortune(paths, |f| {
+        if in_length(&f, min, max) {
+            if seen == index {
+                picked = Some(f.text);
+            } else if picked.is_none() {
+                last = Some(f.text);
+            }
+            seen += 1;
+        }
+    })?;
+    Ok(picked.or(last))
+}
+
 // ------------------------------------------------------------------------------------------------
 #[derive(Debug)]
 pub struct Config {
     sources: Vec<String>,
     pattern: Option<Regex>,
     seed: Option<u64>,
+    min: Option<usize>,
+    max: Option<usize>,
 }
 
 // ------------------------------------------------------------------------------------------------
@@ -144,16 +253,20 @@ pub fn get_config() -> MyResult<Config> {
 
 // ------------------------------------------------------------------------------------------------
 pub fn run(config: Config) -> MyResult<()> {
-    let files = find_files(&config.sources)?;
-    let fortunes = read_fortunes(&files)?;
+    // seed が同じなら引数の並び順によらず同じ結果になるよう、ソースをソートしてから読む
+    let mut sources = config.sources;
+    sources.sort();
+    sources.dedup();
+    let files = find_files(&sources)?;
     match config.pattern {
         Some(pattern) => {
+            let fortunes = filter_by_length(read_fortunes(&files)?, config.min, config.max);
             let mut prev_source = None;
             for fortune in fortunes
                 .iter()
                 .filter(|fortune| pattern.is_match(&fortune.text))
             {
-                if prev_source.as_ref().map_or(true, |s| s != &fortune.source) {
+                if prev_source.as_ref() != Some(&fortune.source) {
                     eprintln!("({})\n%", fortune.source);
                     prev_source = Some(fortune.source.clone())
                 }
@@ -163,7 +276,7 @@ pub fn run(config: Config) -> MyResult<()> {
         _ => {
             println!(
                 "{}",
-                pick_fortune(&fortunes, config.seed)
+                pick_fortune_streaming(&files, config.seed, config.min, config.max)?
                     .or_else(|| Some("No fortunes found".to_string()))
                     .unwrap()
             )
@@ -176,9 +289,12 @@ pub fn run(config: Config) -> MyResult<()> {
 // ------------------------------------------------------------------------------------------------
 #[cfg(test)]
 mod tests {
-    use assert_cmd::assert;
+    use std::path::PathBuf;
 
-    use crate::find_files;
+    use crate::{
+        filter_by_length, find_files, nth_fortune, pick_fortune, pick_fortune_streaming,
+        read_fortunes,
+    };
 
     #[test]
     fn test_find_files() {
@@ -189,7 +305,7 @@ mod tests {
         let files = res.unwrap();
         assert_eq!(files.len(), 1);
         assert_eq!(
-            files.get(0).unwrap().to_string_lossy(),
+            files.first().unwrap().to_string_lossy(),
             "./tests/inputs/jokes"
         );
 
@@ -204,7 +320,7 @@ mod tests {
         // ファイル数とファイルの順番の確認
         let files = res.unwrap();
         assert_eq!(files.len(), 5);
-        let first = files.get(0).unwrap().display().to_string();
+        let first = files.first().unwrap().display().to_string();
         assert!(first.contains("ascii-art"));
         let last = files.last().unwrap().display().to_string();
         assert!(last.contains("quotes"));
@@ -226,4 +342,83 @@ mod tests {
             assert_eq!(filename.to_string_lossy(), "jokes".to_string())
         }
     }
+
+    #[test]
+    fn test_filter_by_length() {
+        let fortunes = || read_fortunes(&[PathBuf::from("./tests/inputs/quotes")]).unwrap();
+        let lens = |fortunes: Vec<_>| {
+            fortunes
+                .iter()
+                .map(|f: &crate::Fortune| f.text.len())
+                .collect::<Vec<_>>()
+        };
+
+        // 指定がなければすべて残る
+        assert_eq!(
+            filter_by_length(fortunes(), None, None).len(),
+            fortunes().len()
+        );
+
+        // 境界値は含む
+        assert_eq!(lens(filter_by_length(fortunes(), None, Some(47))), vec![47]);
+        assert_eq!(
+            lens(filter_by_length(fortunes(), Some(47), Some(53))),
+            vec![47, 53]
+        );
+        assert_eq!(
+            lens(filter_by_length(fortunes(), Some(48), Some(52))),
+            vec![]
+        );
+
+        // 下限だけ
+        let res = lens(filter_by_length(fortunes(), Some(150), None));
+        assert!(!res.is_empty());
+        assert!(res.iter().all(|&len| len >= 150));
+    }
+
+    #[test]
+    fn test_pick_fortune_streaming() {
+        let files = find_files(&["./tests/inputs".to_string()]).unwrap();
+        let fortunes = read_fortunes(&files).unwrap();
+
+        // seed が同じなら、すべて読み込んでから選んだものと同じものを選ぶ
+        for seed in 0..100 {
+            assert_eq!(
+                pick_fortune_streaming(&files, Some(seed), None, None).unwrap(),
+                pick_fortune(&fortunes, Some(seed))
+            );
+        }
+
+        // 長さで絞り込んでも同じ
+        let filtered = filter_by_length(read_fortunes(&files).unwrap(), Some(50), Some(200));
+        for seed in 0..20 {
+            assert_eq!(
+                pick_fortune_streaming(&files, Some(seed), Some(50), Some(200)).unwrap(),
+                pick_fortune(&filtered, Some(seed))
+            );
+        }
+
+        // fortune がなければ選ばない
+        let empty = find_files(&["./tests/inputs/empty".to_string()]).unwrap();
+        assert_eq!(
+            pick_fortune_streaming(&empty, Some(1), None, None).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_nth_fortune() {
+        let files = find_files(&["./tests/inputs".to_string()]).unwrap();
+        let fortunes = read_fortunes(&files).unwrap();
+        assert_eq!(
+            nth_fortune(&files, 1, None, None).unwrap(),
+            Some(fortunes[1].text.clone())
+        );
+
+        // 数えた後にファイルが短くなっても、候補があれば "No fortunes found" にしない
+        assert_eq!(
+            nth_fortune(&files, fortunes.len() + 10, None, None).unwrap(),
+            fortunes.last().map(|f| f.text.clone())
+        );
+    }
 }
@@ -37,7 +37,7 @@ fn dies_bad_file() -> TestResult {
     let bad = gen_bad_file();
     let expected = format!("{}: .* [(]os error 2[)]", bad);
     Command::cargo_bin(PRG)?
-        .args(&[LITERATURE, &bad])
+        .args([LITERATURE, &bad])
         .assert()
         .failure()
         .stderr(predicate::str::is_match(expected)?);
@@ -50,7 +50,7 @@ fn dies_bad_seed() -> TestResult {
     let bad = random_string();
     let expected = format!("\"{}\" not a valid integer", &bad);
     Command::cargo_bin(PRG)?
-        .args(&[LITERATURE, "--seed", &bad])
+        .args([LITERATURE, "--seed", &bad])
         .assert()
         .failure()
         .stderr(predicate::str::contains(expected));
@@ -173,3 +173,81 @@ fn mark_twain_lower_i() -> TestResult {
         "tests/expected/twain_lower_i.err",
     )
 }
+
+// --------------------------------------------------
+fn seeded_output(args: &[&str]) -> Result<String, Box<dyn std::error::Error>> {
+    let output = Command::cargo_bin(PRG)?.args(args).output()?;
+    assert!(output.status.success());
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+// --------------------------------------------------
+#[test]
+fn same_seed_same_fortune() -> TestResult {
+    let args = [JOKES, LITERATURE, QUOTES, "-s", "3"];
+    let first = seeded_output(&args)?;
+    for _ in 0..3 {
+        assert_eq!(seeded_output(&args)?, first);
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn seed_ignores_source_order() -> TestResult {
+    let expected = seeded_output(&[JOKES, LITERATURE, QUOTES, "-s", "3"])?;
+    assert_eq!(
+        seeded_output(&[QUOTES, JOKES, LITERATURE, "-s", "3"])?,
+        expected
+    );
+    assert_eq!(
+        seeded_output(&[LITERATURE, QUOTES, JOKES, "-s", "3"])?,
+        expected
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_min_greater_than_max() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([QUOTES, "--min", "60", "--max", "50"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "--min (60) must not be greater than --max (50)",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn quotes_max_length() -> TestResult {
+    // 50 バイト以下は "It's like deja vu all over again." だけ
+    run(
+        &[QUOTES, "-s", "1", "--max", "50"],
+        "It's like deja vu all over again.\n-- Yogi Berra\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn quotes_length_window() -> TestResult {
+    // 範囲外のものは候補に残らない
+    run(
+        &[QUOTES, "--min", "48", "--max", "52"],
+        "No fortunes found\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn pattern_length_window() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([QUOTES, "-m", "Yogi Berra", "--min", "50", "--max", "60"])
+        .assert()
+        .success()
+        .stderr("(quotes)\n%\n")
+        .stdout("You can observe a lot just by watching.\n-- Yogi Berra\n%\n");
+    Ok(())
+}
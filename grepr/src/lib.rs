@@ -1,17 +1,62 @@
-use std::{error::Error, fs::{self, File}, io::{self, BufRead, BufReader}, iter::once};
+use std::{collections::VecDeque, error::Error, fs::{self, File}, io::{self, BufRead, BufReader, IsTerminal}, iter::once, path::Path};
 
-use clap::Parser;
+use ansi_term::{Colour, Style};
+use clap::{Parser, ValueEnum};
 use regex::{Regex, RegexBuilder};
-use walkdir::WalkDir;
+use ignore::WalkBuilder;
+use serde_json::{json, Value};
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ColorWhen {
+    Auto,
+    Always,
+    Never,
+}
+
+// マッチした部分の色
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum MatchColor {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+}
+
+impl MatchColor {
+    fn colour(self) -> Colour {
+        match self {
+            MatchColor::Red => Colour::Red,
+            MatchColor::Green => Colour::Green,
+            MatchColor::Yellow => Colour::Yellow,
+            MatchColor::Blue => Colour::Blue,
+        }
+    }
+}
+
 #[derive(Debug, Parser)]
 #[command(version, about)]
 pub struct Args {
     /// Search pattern
-    #[arg(value_name = "PATTERN")]
-    pattern: String,
+    #[arg(value_name = "PATTERN", required_unless_present_any = ["regexps", "pattern_files"])]
+    pattern: Option<String>,
+
+    /// Search pattern (can be given multiple times)
+    #[arg(short = 'e', long = "regexp", value_name = "PATTERN")]
+    regexps: Option<Vec<String>>,
+
+    /// Read patterns from FILE, one per line (can be given multiple times)
+    #[arg(short = 'f', long = "file", value_name = "FILE")]
+    pattern_files: Option<Vec<String>>,
+
+    /// Interpret patterns as fixed strings
+    #[arg(short = 'F', long = "fixed-strings")]
+    fixed_strings: bool,
+
+    /// Select only matches that span the whole line
+    #[arg(short = 'x', long = "line-regexp")]
+    line_regexp: bool,
 
     /// Input file(s) [stdin is selected if not specified]
     #[arg(value_name = "FILE")]
@@ -21,32 +66,194 @@ pub struct Args {
     #[arg(short, long)]
     recursive: bool,
 
+    /// Do not skip files ignored by .gitignore (and .git/) in recursive search
+    #[arg(long = "no-ignore")]
+    no_ignore: bool,
+
     /// Count occurrence
-    #[arg(short, long)]
+    #[arg(short, long, conflicts_with_all = ["files_with_matches", "files_without_match"])]
     count: bool,
 
+    /// Count every match instead of matching lines (with -v, count non-matching lines)
+    #[arg(long = "count-matches", conflicts_with_all = ["count", "files_with_matches", "files_without_match"])]
+    count_matches: bool,
+
+    /// Print only names of files with a match
+    #[arg(short = 'l', long = "files-with-matches", conflicts_with = "files_without_match")]
+    files_with_matches: bool,
+
+    /// Print only names of files without a match
+    #[arg(short = 'L', long = "files-without-match")]
+    files_without_match: bool,
+
     /// Invert match
     #[arg(short = 'v', long = "invert-match")]
     invert_match: bool,
 
+    /// Print only the matched parts of matching lines, each on its own line
+    #[arg(short = 'o', long = "only-matching", conflicts_with_all = ["invert_match", "count_matches"])]
+    only_matching: bool,
+
+    /// With -o, print only capture group N of each match
+    #[arg(long, value_name = "N", requires = "only_matching")]
+    group: Option<usize>,
+
+    /// Print matching lines with every match replaced by TEMPLATE ($1, ${name} refer to capture groups)
+    #[arg(long, value_name = "TEMPLATE", conflicts_with_all = ["count", "count_matches", "only_matching"])]
+    replace: Option<String>,
+
+    /// Print each matching (and context) line as a JSON object, one per line
+    #[arg(
+        long,
+        conflicts_with_all = ["count", "count_matches", "files_with_matches", "files_without_match", "only_matching", "replace"]
+    )]
+    json: bool,
+
     /// Case-insensitive
     #[arg(short, long)]
     insensitive: bool,
+
+    /// Print nothing, exit with 0 if any line matches
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Print the number of files searched, files with matches and matching lines to stderr at the end
+    #[arg(long, conflicts_with_all = ["quiet", "files_with_matches", "files_without_match"])]
+    stats: bool,
+
+    /// Prefix each line with its line number
+    #[arg(short = 'n', long = "line-number")]
+    line_number: bool,
+
+    /// Print CRLF-terminated lines with LF only
+    #[arg(long = "strip-cr")]
+    strip_cr: bool,
+
+    /// Treat input as NUL-terminated records instead of lines ('.' also matches newlines)
+    #[arg(short = 'z', long = "null-data")]
+    null_data: bool,
+
+    /// Print NUM lines of trailing context after matching lines
+    #[arg(short = 'A', long = "after-context", value_name = "NUM")]
+    after_context: Option<usize>,
+
+    /// Print NUM lines of leading context before matching lines
+    #[arg(short = 'B', long = "before-context", value_name = "NUM")]
+    before_context: Option<usize>,
+
+    /// Print NUM lines of output context
+    #[arg(short = 'C', long = "context", value_name = "NUM")]
+    context: Option<usize>,
+
+    /// Print SEP between groups of lines and between files [default: --]
+    #[arg(long = "group-separator", value_name = "SEP")]
+    group_separator: Option<String>,
+
+    /// Do not print a separator between groups of context lines
+    #[arg(long = "no-group-separator")]
+    no_group_separator: bool,
+
+    /// Highlight matching strings
+    #[arg(
+        long,
+        value_name = "WHEN",
+        value_enum,
+        default_value = "never",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "auto"
+    )]
+    color: ColorWhen,
+
+    /// Color used to highlight matching strings (with --color)
+    #[arg(long = "match-color", value_name = "COLOR", value_enum, default_value = "red")]
+    match_color: MatchColor,
 }
 
 impl Args {
+    #[allow(clippy::wrong_self_convention)]
     fn to_config(self) -> MyResult<Config> {
-        let pattern = RegexBuilder::new(&self.pattern)
+        // -e か -f があるときは、位置引数の最初のものもファイルとして扱う
+        let explicit = self.regexps.is_some() || self.pattern_files.is_some();
+        let mut patterns = self.regexps.unwrap_or_default();
+        for path in self.pattern_files.unwrap_or_default() {
+            patterns.extend(read_patterns(&path)?);
+        }
+        let files = match self.pattern {
+            Some(file) if explicit => {
+                Some(once(file).chain(self.files.unwrap_or_default()).collect())
+            }
+            Some(pattern) => {
+                patterns.push(pattern);
+                self.files
+            }
+            None if explicit => self.files,
+            None => return Err(From::from("No pattern given")),
+        };
+
+        let escaped: Vec<String> = patterns
+            .iter()
+            .map(|pattern| if self.fixed_strings { regex::escape(pattern) } else { pattern.to_string() })
+            .collect();
+        // 複数のパターンはまとめて1つの選択にする
+        // キャプチャグループの番号がずれないように非キャプチャグループで囲む
+        // パターンファイルが空のときは grep と同じく何にもマッチしない
+        let combined = match escaped.as_slice() {
+            [] => r"[^\s\S]".to_string(),
+            [pattern] => pattern.to_string(),
+            _ => format!("(?:{})", escaped.join("|")),
+        };
+        // -x のときは行全体にマッチするものだけにする
+        let combined = if self.line_regexp { format!("^(?:{})$", combined) } else { combined };
+
+        // -z のときはレコードが複数行にわたるので、'.' が改行にもマッチするようにする
+        let pattern = RegexBuilder::new(&combined)
             .case_insensitive(self.insensitive)
+            .dot_matches_new_line(self.null_data)
             .build()
-            .map_err(|_| format!("Invalid pattern \"{}\"", self.pattern))?;
+            .map_err(|_| format!("Invalid pattern \"{}\"", patterns.join("|")))?;
 
         Ok(Config {
             pattern,
-            files: self.files,
+            files,
             recursive: self.recursive,
+            no_ignore: self.no_ignore,
             count: self.count,
+            count_matches: self.count_matches,
+            files_with_matches: self.files_with_matches,
+            files_without_match: self.files_without_match,
             invert_match: self.invert_match,
+            only_matching: self.only_matching,
+            group: self.group.unwrap_or(0),
+            replace: self.replace,
+            json: self.json,
+            quiet: self.quiet,
+            stats: self.stats,
+            line_number: self.line_number,
+            strip_cr: self.strip_cr,
+            null_data: self.null_data,
+            // -A, -B の指定は -C より優先する
+            before_context: self.before_context.or(self.context).unwrap_or(0),
+            after_context: self.after_context.or(self.context).unwrap_or(0),
+            // 区切りは文脈を指定したとき (-A 0 なども含む) か、--group-separator を明示したときに出す
+            group_separator: if self.no_group_separator {
+                None
+            } else if self.group_separator.is_some()
+                || self.before_context.is_some()
+                || self.after_context.is_some()
+                || self.context.is_some()
+            {
+                Some(self.group_separator.unwrap_or_else(|| "--".to_string()))
+            } else {
+                None
+            },
+            // auto のときは端末に出力する場合だけ色を付ける
+            color: match self.color {
+                ColorWhen::Always => true,
+                ColorWhen::Never => false,
+                ColorWhen::Auto => io::stdout().is_terminal(),
+            }
+            .then(|| Style::new().reverse().fg(self.match_color.colour())),
         })
     }
 }
@@ -56,75 +263,247 @@ pub struct Config {
     pattern: Regex,
     files: Option<Vec<String>>,
     recursive: bool,
+    // 再帰検索で .gitignore を無視するか
+    no_ignore: bool,
     count: bool,
+    count_matches: bool,
+    files_with_matches: bool,
+    files_without_match: bool,
     invert_match: bool,
+    only_matching: bool,
+    // -o で出すキャプチャグループの番号。0 ならマッチ全体
+    group: usize,
+    // 指定されていれば、マッチした行の中のマッチをこのテンプレートで置き換えて出す
+    replace: Option<String>,
+    // マッチした行を JSON Lines で出す
+    json: bool,
+    quiet: bool,
+    stats: bool,
+    line_number: bool,
+    // CRLF の行を出力するときに LF だけにする
+    strip_cr: bool,
+    // 行の代わりに NUL で区切ったレコードを単位にする
+    null_data: bool,
+    before_context: usize,
+    after_context: usize,
+    // グループの間やファイルの間に出す区切り。None なら区切りを出さない
+    group_separator: Option<String>,
+    // マッチした部分に付ける色。None なら色を付けない
+    color: Option<Style>,
+}
+
+impl Config {
+    // 1レコードの終わりを表すバイト
+    fn terminator(&self) -> u8 {
+        if self.null_data { b'\0' } else { b'\n' }
+    }
+}
+
+// 出力する行
+// 前後の文脈の行と、離れたグループの間に入れる区切りも含む
+#[derive(Debug, PartialEq)]
+enum Output {
+    Match(usize, String),
+    Context(usize, String),
+    Separator,
+}
+
+// -f で指定されたファイルから1行1パターンとして読む
+// 空行は grep と同じくすべての行にマッチするパターンとしてそのまま残す
+fn read_patterns(path: &str) -> MyResult<Vec<String>> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+    let patterns = BufReader::new(file)
+        .lines()
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("{}: {}", path, e))?;
+    Ok(patterns)
 }
 
 pub fn get_config() -> MyResult<Config> {
     Args::parse().to_config()
 }
 
-fn find_files(paths: &[String], recursive: bool) -> Vec<MyResult<String>> {
-    let aux = |path: &String| -> Box<dyn Iterator<Item = MyResult<String>>> {
+// 再帰検索では、no_ignore でなければ .gitignore などで除外されたパスと .git/ を飛ばす
+// dotfile はこれまでどおり検索する
+// エラーも、起きたパス (わからなければ引数のパス) の位置に並ぶように、並べ替えのキーと組にする
+fn find_files(paths: &[String], recursive: bool, no_ignore: bool) -> Vec<MyResult<String>> {
+    let aux = |path: &String| -> Box<dyn Iterator<Item = (String, MyResult<String>)>> {
         match fs::metadata(path) {
             Ok(metadata) => {
                 if metadata.is_file() {
-                    Box::new(once(Ok(path.to_string())))
+                    Box::new(once((path.to_string(), Ok(path.to_string()))))
                 } else if metadata.is_dir() {
                     if recursive {
-                        let iter = WalkDir::new(path)
-                            .into_iter()
-                            .filter_map(|dir_entry| {
+                        let dir = path.to_string();
+                        let mut builder = WalkBuilder::new(path);
+                        builder.standard_filters(!no_ignore).hidden(false).require_git(false);
+                        if !no_ignore {
+                            builder.filter_entry(|entry| entry.file_name() != ".git");
+                        }
+                        let iter = builder
+                            .build()
+                            .filter_map(move |dir_entry| {
                                 match dir_entry {
-                                    Ok(entry) if entry.file_type().is_file() => {
-                                        Some(Ok(entry.path().to_string_lossy().into_owned()))
+                                    Ok(entry) if entry.file_type().is_some_and(|t| t.is_file()) => {
+                                        let path = entry.path().to_string_lossy().into_owned();
+                                        Some((path.clone(), Ok(path)))
                                     },
                                     Ok(_) => None,
-                                    Err(e) => Some(Err(From::from(e))),
+                                    Err(e) => {
+                                        let key = walk_error_path(&e)
+                                            .map_or_else(|| dir.clone(), |path| path.to_string_lossy().into_owned());
+                                        Some((key, Err(From::from(e))))
+                                    },
                                 }
                             });
                         
                         Box::new(iter)
                     } else {
-                        Box::new(once(Err(From::from(
+                        Box::new(once((path.to_string(), Err(From::from(
                             format!("{} is a directory", path)
-                        ))))
+                        )))))
                     }
                 } else {
-                    Box::new(once(Err(From::from(
+                    Box::new(once((path.to_string(), Err(From::from(
                         format!("{} this is not file or dir. Maybe link?", path)
-                    ))))
+                    )))))
                 }
             },
             Err(e) => {
-                Box::new(once(Err(From::from(
+                Box::new(once((path.to_string(), Err(From::from(
                     format!("{}: {}", path, e)
-                ))))
+                )))))
             }
         }
     };
 
-    paths
-        .into_iter()
-        .flat_map(|path| aux(path))
-        .collect()
+    // 同じファイルを2回検索しないように、並べ替えて重複を除く
+    // エラーは消さずに、キーの位置に残す (安定ソートなので同じキーなら見つけた順)
+    let mut entries: Vec<_> = paths.iter().flat_map(aux).collect();
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+    entries.dedup_by(|(a, a_res), (b, b_res)| a == b && a_res.is_ok() && b_res.is_ok());
+
+    entries.into_iter().map(|(_, entry)| entry).collect()
+}
+
+// 再帰検索のエラーが起きたパス
+fn walk_error_path(err: &ignore::Error) -> Option<&Path> {
+    match err {
+        ignore::Error::WithPath { path, .. } => Some(path),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => walk_error_path(err),
+        _ => None,
+    }
+}
+
+// terminator までを1レコード (1行) として buf に読み込む。terminator も含める
+// NUL 区切りのときは、出力も NUL 区切りにそろうように、最後のレコードにも terminator を補う
+fn read_record<T: BufRead>(file: &mut T, buf: &mut String, terminator: u8) -> io::Result<usize> {
+    if terminator == b'\n' {
+        return file.read_line(buf);
+    }
+
+    let mut bytes = Vec::new();
+    let num = file.read_until(terminator, &mut bytes)?;
+    let record = String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    buf.push_str(&record);
+    if num > 0 && !record.ends_with(terminator as char) {
+        buf.push(terminator as char);
+    }
+    Ok(num)
+}
+
+// レコードから終端 (CRLF、LF、-z のときは NUL) を1つ取り除いたもの
+// マッチはこれに対して行うので、CRLF のファイルでも `$` や -x が行末にマッチする
+fn body(line: &str) -> &str {
+    line.strip_suffix('\0')
+        .or_else(|| line.strip_suffix("\r\n"))
+        .or_else(|| line.strip_suffix('\n'))
+        .unwrap_or(line)
 }
 
 fn find_lines<T: BufRead> (
+    file: T,
+    pattern: &Regex,
+    invert_match: bool,
+    terminator: u8,
+) -> MyResult<Vec<(usize, String)>> {
+
+    let result = find_lines_with_context(file, pattern, invert_match, 0, 0, terminator)?
+        .into_iter()
+        .filter_map(|output| match output {
+            Output::Match(num, line) => Some((num, line)),
+            _ => None,
+        })
+        .collect();
+
+    Ok(result)
+}
+
+// マッチする行が1つでもあるか
+// 最初にマッチした時点で読むのをやめる
+fn has_match<T: BufRead>(mut file: T, pattern: &Regex, invert_match: bool, terminator: u8) -> MyResult<bool> {
+    let mut line_buf = String::new();
+
+    loop {
+        line_buf.clear();
+        let bytes = read_record(&mut file, &mut line_buf, terminator)?;
+        if bytes == 0 { return Ok(false); }
+
+        if pattern.is_match(body(&line_buf)) != invert_match {
+            return Ok(true);
+        }
+    }
+}
+
+// マッチした行の前 before 行、後ろ after 行も一緒に返す
+// 直前の before 行だけを保持しながら読むので、ファイル全体をメモリに載せない
+fn find_lines_with_context<T: BufRead> (
     mut file: T,
     pattern: &Regex,
     invert_match: bool,
-) -> MyResult<Vec<String>> {
+    before: usize,
+    after: usize,
+    terminator: u8,
+) -> MyResult<Vec<Output>> {
 
-    let mut result: Vec<String> = Vec::new();
+    let mut result: Vec<Output> = Vec::new();
+    let mut before_lines: VecDeque<(usize, String)> = VecDeque::new();
+    let mut after_left = 0;
+    let mut last_output: Option<usize> = None;
+    let mut line_num = 0;
 
     loop {
         let mut line_buf = String::new();
-        let bytes = file.read_line(&mut line_buf)?;
+        let bytes = read_record(&mut file, &mut line_buf, terminator)?;
         if bytes == 0 { break; }
+        // 行番号は 1 始まり
+        line_num += 1;
 
-        match (pattern.is_match(&line_buf), invert_match) {
-            (true, false) | (false, true) => { result.push(line_buf) }
+        match (pattern.is_match(body(&line_buf)), invert_match) {
+            (true, false) | (false, true) => {
+                // 前のグループと連続していなければ区切りを入れる (出すかどうかは print_lines が決める)
+                let first = before_lines.front().map_or(line_num, |(num, _)| *num);
+                if last_output.is_some_and(|last| first > last + 1) {
+                    result.push(Output::Separator);
+                }
+                for (num, line) in before_lines.drain(..) {
+                    result.push(Output::Context(num, line));
+                }
+                result.push(Output::Match(line_num, line_buf));
+                last_output = Some(line_num);
+                after_left = after;
+            }
+            _ if after_left > 0 => {
+                result.push(Output::Context(line_num, line_buf));
+                last_output = Some(line_num);
+                after_left -= 1;
+            }
+            _ if before > 0 => {
+                before_lines.push_back((line_num, line_buf));
+                if before_lines.len() > before {
+                    before_lines.pop_front();
+                }
+            }
             _ => {}
         }
     }
@@ -132,17 +511,221 @@ fn find_lines<T: BufRead> (
     Ok(result)
 }
 
-fn print_lines(header: Option<&str>, lines: Vec<String>, count: bool) {
-    let header = if let Some(file) = header { format!("{}:", file) } else { "".to_string() };
+// 行の中のマッチした部分に色を付ける
+// 改行 (-z のときは NUL) はマッチの対象にしない
+fn highlight(line: &str, pattern: &Regex, style: Style) -> String {
+    let body = body(line);
+    let mut result = String::new();
+    let mut last = 0;
+
+    for mat in pattern.find_iter(body).filter(|mat| !mat.is_empty()) {
+        result.push_str(&body[last..mat.start()]);
+        result.push_str(&style.paint(mat.as_str()).to_string());
+        last = mat.end();
+    }
+    result.push_str(&line[last..]);
+
+    result
+}
+
+// 行の中のマッチした部分 (group が 0 でなければそのキャプチャグループ) を順に返す
+// 空のマッチと、そのマッチで参加しなかったグループは飛ばす
+fn only_matching<'a>(line: &'a str, pattern: &Regex, group: usize) -> Vec<&'a str> {
+    let body = body(line);
+    pattern
+        .captures_iter(body)
+        .filter_map(|caps| caps.get(group))
+        .map(|mat| mat.as_str())
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+// 行の中のマッチをすべて template で置き換える ($1 などでキャプチャグループを参照できる)
+// 改行 (-z のときは NUL) はマッチの対象にしない
+fn replace_line(line: &str, pattern: &Regex, template: &str) -> String {
+    let body = body(line);
+    format!("{}{}", pattern.replace_all(body, template), &line[body.len()..])
+}
 
-    if count {
-        println!("{}{}", header, lines.len());
+// 1行分の出力を JSON のオブジェクトにする。区切りは None
+// line には改行 (-z のときは NUL) を含めない
+// submatches は line の中でマッチした部分のバイト範囲 (end は含まない)。文脈の行と -v のときは空
+fn json_record(path: &str, output: &Output, pattern: &Regex, invert_match: bool) -> Option<Value> {
+    let (kind, num, line) = match output {
+        Output::Match(num, line) => ("match", num, line),
+        Output::Context(num, line) => ("context", num, line),
+        Output::Separator => return None,
+    };
+    let body = body(line);
+    let submatches: Vec<Value> = if kind == "match" && !invert_match {
+        pattern
+            .find_iter(body)
+            .filter(|mat| !mat.is_empty())
+            .map(|mat| json!({ "match": mat.as_str(), "start": mat.start(), "end": mat.end() }))
+            .collect()
     } else {
-        for line in lines {
+        Vec::new()
+    };
+
+    Some(json!({
+        "type": kind,
+        "path": path,
+        "line_number": num,
+        "line": body,
+        "submatches": submatches,
+    }))
+}
+
+// grep と同じく、マッチした行は ':'、文脈の行は '-' でファイル名や行番号と区切る
+fn print_lines(
+    header: Option<&str>,
+    lines: Vec<Output>,
+    line_number: bool,
+    color: Option<(&Regex, Style)>,
+    separator: Option<&str>,
+) {
+    for output in lines {
+        let (num, line, sep) = match output {
+            Output::Match(num, line) => match color {
+                Some((pattern, style)) => (num, highlight(&line, pattern, style), ':'),
+                None => (num, line, ':'),
+            },
+            Output::Context(num, line) => (num, line, '-'),
+            Output::Separator => {
+                if let Some(separator) = separator {
+                    println!("{}", separator);
+                }
+                continue;
+            }
+        };
+        let header = if let Some(file) = header { format!("{}{}", file, sep) } else { "".to_string() };
+
+        if line_number {
+            print!("{}{}{}{}", header, num, sep, line)
+        } else {
             print!("{}{}", header, line)
         }
     }
+}
+
+// マッチした行 (-v のときはマッチしなかった行) の数を返す
+// --quiet, -l, -L のときは最初にマッチした時点で読むのをやめるので、マッチがあれば 1 を返す
+// --quiet のときは何も出力しない
+// --count のときは文脈は無視してマッチした行数だけを出す
+// --count-matches のときは行数ではなくマッチの数を出す
+// -l, -L のときは行は出さずにファイル名だけを出す
+// -o のときはマッチした部分だけを1つずつ出す。文脈は出さない
+// --json のときは文脈の行も含めて1行ずつ JSON で出す。区切りは出さない
+// --replace のときはマッチした行を置き換えてから出す。文脈の行はそのまま
+// --strip-cr のときは、マッチした行も文脈の行も CRLF を LF にして出す
+// 前のファイルで何か出力していれば (printed) 先頭に区切りを入れる
+fn grep_file(
+    config: &Config,
+    file: impl BufRead,
+    filename: &str,
+    show_header: bool,
+    printed: &mut bool,
+) -> MyResult<usize> {
+    let header = if show_header { Some(filename) } else { None };
+
+    if config.quiet {
+        return Ok(has_match(file, &config.pattern, config.invert_match, config.terminator())? as usize);
+    }
 
+    if config.files_with_matches || config.files_without_match {
+        let matched = has_match(file, &config.pattern, config.invert_match, config.terminator())?;
+        if matched == config.files_with_matches {
+            println!("{}", filename);
+        }
+        Ok(matched as usize)
+    } else if config.count || config.count_matches {
+        let result_lines = find_lines(file, &config.pattern, config.invert_match, config.terminator())?;
+        // -v のときは1行あたりのマッチの数に意味がないので、--count-matches でもマッチしない行を数える
+        let num = if config.count_matches && !config.invert_match {
+            result_lines
+                .iter()
+                .map(|(_, line)| config.pattern.find_iter(body(line)).count())
+                .sum()
+        } else {
+            result_lines.len()
+        };
+        let header = if let Some(file) = header { format!("{}:", file) } else { "".to_string() };
+        println!("{}{}", header, num);
+        Ok(result_lines.len())
+    } else if config.only_matching {
+        let result_lines = find_lines(file, &config.pattern, false, config.terminator())?;
+        let header = if let Some(file) = header { format!("{}:", file) } else { "".to_string() };
+        for (num, line) in &result_lines {
+            for part in only_matching(line, &config.pattern, config.group) {
+                let part = match config.color {
+                    Some(style) => style.paint(part).to_string(),
+                    None => part.to_string(),
+                };
+                // -z のときはマッチした部分も NUL で区切る
+                let end = config.terminator() as char;
+                if config.line_number {
+                    print!("{}{}:{}{}", header, num, part, end);
+                } else {
+                    print!("{}{}{}", header, part, end);
+                }
+            }
+        }
+        Ok(result_lines.len())
+    } else if config.json {
+        let result_lines = find_lines_with_context(
+            file,
+            &config.pattern,
+            config.invert_match,
+            config.before_context,
+            config.after_context,
+            config.terminator(),
+        )?;
+        for output in &result_lines {
+            if let Some(record) = json_record(filename, output, &config.pattern, config.invert_match) {
+                println!("{}", record);
+            }
+        }
+        Ok(result_lines.iter().filter(|output| matches!(output, Output::Match(..))).count())
+    } else {
+        let mut result_lines = find_lines_with_context(
+            file,
+            &config.pattern,
+            config.invert_match,
+            config.before_context,
+            config.after_context,
+            config.terminator(),
+        )?;
+        if let Some(template) = &config.replace {
+            for output in result_lines.iter_mut() {
+                if let Output::Match(_, line) = output {
+                    *line = replace_line(line, &config.pattern, template);
+                }
+            }
+        }
+        if config.strip_cr {
+            for output in result_lines.iter_mut() {
+                if let Output::Match(_, line) | Output::Context(_, line) = output {
+                    if line.ends_with("\r\n") {
+                        line.remove(line.len() - 2);
+                    }
+                }
+            }
+        }
+        let matched = result_lines.iter().filter(|output| matches!(output, Output::Match(..))).count();
+        if *printed && !result_lines.is_empty() {
+            result_lines.insert(0, Output::Separator);
+        }
+        *printed |= !result_lines.is_empty();
+        print_lines(
+            header,
+            result_lines,
+            config.line_number,
+            // 置き換えた後の行にはもとのマッチの位置が残っていないので、色は付けない
+            config.color.filter(|_| config.replace.is_none()).map(|style| (&config.pattern, style)),
+            config.group_separator.as_deref(),
+        );
+        Ok(matched)
+    }
 }
 
 fn open(input: Option<&str>) -> MyResult<Box<dyn BufRead>> {
@@ -152,34 +735,71 @@ fn open(input: Option<&str>) -> MyResult<Box<dyn BufRead>> {
     }
 }
 
-pub fn run(config: Config) -> MyResult<()> {
+// ファイルをまたいだ集計 (--stats と終了コードに使う)
+#[derive(Debug, Default)]
+struct Stats {
+    files_searched: usize,
+    files_matched: usize,
+    lines_matched: usize,
+}
 
-    match config.files {
+impl Stats {
+    // num はそのファイルでマッチした行の数
+    fn add(&mut self, num: usize) {
+        self.files_searched += 1;
+        if num > 0 {
+            self.files_matched += 1;
+        }
+        self.lines_matched += num;
+    }
+}
+
+// grep と同じく、何か選ばれたものがあれば true を返す (main で終了コードにする)
+// ふつうは1行でもマッチした行があれば、-L のときはマッチしなかったファイルを1つでも出せば true
+// --quiet のときは最初にマッチした時点で終える
+// --stats のときは最後に検索したファイル数、マッチしたファイル数、マッチした行数を stderr に出す
+pub fn run(config: Config) -> MyResult<bool> {
+    // これまでに何か行を出力したか
+    let mut printed = false;
+    let mut stats = Stats::default();
+
+    match &config.files {
         None => {
             let buf_reader = open(None)?;
-            let result_lines = find_lines(buf_reader, &config.pattern, config.invert_match)?;
-            print_lines(None, result_lines, config.count);
+            stats.add(grep_file(&config, buf_reader, "(standard input)", false, &mut printed)?);
         },
         Some(paths) => {
-            let files = find_files(&paths, config.recursive);
+            let files = find_files(paths, config.recursive, config.no_ignore);
+            // 重複を除く前の引数で決める (同じファイルを2回渡してもファイル名を付ける)
+            let show_header = paths.len() > 1 || files.len() > 1;
             for entry in &files {
                 match entry {
                     Err(e) => eprintln!("{}", e),
                     Ok(filename) => {
-                        let buf_reader = open(Some(&filename))?;
-                        let result_lines = find_lines(buf_reader, &config.pattern, config.invert_match)?;
-                        print_lines(
-                            if files.len()>1 { Some(&filename) } else { None },
-                            result_lines,
-                            config.count
-                        )
+                        let buf_reader = open(Some(filename))?;
+                        stats.add(grep_file(&config, buf_reader, filename, show_header, &mut printed)?);
+                        if stats.files_matched > 0 && config.quiet {
+                            break;
+                        }
                     }
                 }
             }
         }
     }
 
-    Ok(())
+    if config.stats {
+        eprintln!(
+            "{} files searched, {} files matched, {} matching lines",
+            stats.files_searched, stats.files_matched, stats.lines_matched
+        );
+    }
+
+    let selected = if config.files_without_match {
+        stats.files_searched > stats.files_matched
+    } else {
+        stats.files_matched > 0
+    };
+    Ok(selected)
 }
 
 
@@ -188,26 +808,30 @@ mod tests {
     use std::io::Cursor;
 
 
-    use super::{find_files, find_lines};
+    use super::{
+        body, find_files, find_lines, find_lines_with_context, has_match, highlight, json_record, only_matching,
+        replace_line, Output,
+    };
+    use ansi_term::{Colour, Style};
     use rand::{distributions::Alphanumeric, Rng};
     use regex::{Regex, RegexBuilder};
 
     #[test]
     fn test_find_files() {
         // 存在するファイルを見つけられる
-        let files = find_files(&["./tests/inputs/fox.txt".to_string()], false);
+        let files = find_files(&["./tests/inputs/fox.txt".to_string()], false, false);
         assert_eq!(files.len(), 1);
         assert_eq!(files[0].as_ref().unwrap(), "./tests/inputs/fox.txt");
 
         // recursiveなしの場合、ディレクトリを拒否する
-        let files = find_files(&["./tests/inputs".to_string()], false);
+        let files = find_files(&["./tests/inputs".to_string()], false, false);
         assert_eq!(files.len(), 1);
         if let Err(e) = &files[0] {
             assert_eq!(e.to_string(), "./tests/inputs is a directory");
         }
 
         // ディレクトリ内の4つのファイルを再帰的に検索できることを確認する
-        let res = find_files(&["./tests/inputs".to_string()], true);
+        let res = find_files(&["./tests/inputs".to_string()], true, false);
         let mut files: Vec<String> = res
             .iter()
             .map(|r| r.as_ref().unwrap().replace("\\", "/"))
@@ -231,23 +855,72 @@ mod tests {
             .map(char::from)
             .collect();
 
-        let files = find_files(&[bad], false);
+        let files = find_files(&[bad], false, false);
         assert_eq!(files.len(), 1);
         assert!(files[0].is_err());
     }
 
+    #[test]
+    fn test_find_files_dedup() {
+        // 重なったパスを渡しても、各ファイルは1回だけ、並べ替えた順で返る
+        let res = find_files(
+            &[
+                "tests/inputs/nobody.txt".to_string(),
+                "tests/inputs".to_string(),
+                "tests/inputs/fox.txt".to_string(),
+                "tests/inputs/fox.txt".to_string(),
+            ],
+            true,
+            false,
+        );
+        let files: Vec<String> = res
+            .iter()
+            .map(|r| r.as_ref().unwrap().replace("\\", "/"))
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                "tests/inputs/bustle.txt",
+                "tests/inputs/empty.txt",
+                "tests/inputs/fox.txt",
+                "tests/inputs/nobody.txt",
+            ]
+        );
+
+        // エラーは残る
+        let res = find_files(&["tests/inputs".to_string(), "tests/inputs/fox.txt".to_string()], false, false);
+        assert_eq!(res.len(), 2);
+        assert!(res[0].is_err());
+        assert_eq!(res[1].as_ref().unwrap(), "tests/inputs/fox.txt");
+
+        // エラーは先頭にまとめず、パスの順の位置に並ぶ
+        let res = find_files(
+            &[
+                "tests/inputs/nobody.txt".to_string(),
+                "tests/inputs/missing.txt".to_string(),
+                "tests/inputs/bustle.txt".to_string(),
+            ],
+            false,
+            false,
+        );
+        assert_eq!(res.len(), 3);
+        assert_eq!(res[0].as_ref().unwrap(), "tests/inputs/bustle.txt");
+        assert!(res[1].as_ref().is_err_and(|e| e.to_string().starts_with("tests/inputs/missing.txt: ")));
+        assert_eq!(res[2].as_ref().unwrap(), "tests/inputs/nobody.txt");
+    }
+
     #[test]
     fn test_find_lines() {
         let text = b"Lorem\nIpsum\r\nDOLOR";
         
         // "or"
         let re1 = Regex::new("or").unwrap();
-        let matches = find_lines(Cursor::new(&text), &re1, false);
+        let matches = find_lines(Cursor::new(&text), &re1, false, b'\n');
         assert!(matches.is_ok());
         assert_eq!(matches.unwrap().len(), 1);
 
         // "or" でマッチを反転
-        let matches = find_lines(Cursor::new(&text), &re1, true);
+        let matches = find_lines(Cursor::new(&text), &re1, true, b'\n');
         assert!(matches.is_ok());
         assert_eq!(matches.unwrap().len(), 2);
 
@@ -258,13 +931,256 @@ mod tests {
             .unwrap();
 
         // "or"
-        let matches = find_lines(Cursor::new(&text), &re2, false);
+        let matches = find_lines(Cursor::new(&text), &re2, false, b'\n');
         assert!(matches.is_ok());
         assert_eq!(matches.unwrap().len(), 2);
 
         // "or" でマッチを反転
-        let matches = find_lines(Cursor::new(&text), &re2, true);
+        let matches = find_lines(Cursor::new(&text), &re2, true, b'\n');
         assert!(matches.is_ok());
         assert_eq!(matches.unwrap().len(), 1);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_find_lines_line_numbers() {
+        let text = b"Lorem\nIpsum\r\nDOLOR";
+
+        // マッチした行の 1 始まりの行番号が返る
+        let re = RegexBuilder::new("or")
+            .case_insensitive(true)
+            .build()
+            .unwrap();
+        let matches = find_lines(Cursor::new(&text), &re, false, b'\n').unwrap();
+        assert_eq!(
+            matches,
+            vec![(1, "Lorem\n".to_string()), (3, "DOLOR".to_string())]
+        );
+
+        // 反転しても元の行番号のまま
+        let matches = find_lines(Cursor::new(&text), &re, true, b'\n').unwrap();
+        assert_eq!(matches, vec![(2, "Ipsum\r\n".to_string())]);
+    }
+
+    #[test]
+    fn test_find_lines_null_data() {
+        // NUL で区切ったレコードごとに調べる。レコードの中の改行はそのまま残る
+        let text = b"begin\nfoo\nend\0begin\nbar\nend\0begin\nfoo";
+        let re = RegexBuilder::new(r"foo.end").dot_matches_new_line(true).build().unwrap();
+        let matches = find_lines(Cursor::new(&text), &re, false, b'\0').unwrap();
+        assert_eq!(matches, vec![(1, "begin\nfoo\nend\0".to_string())]);
+
+        // 最後のレコードにも NUL を補う
+        let re = Regex::new(r"begin\nfoo").unwrap();
+        let matches = find_lines(Cursor::new(&text), &re, false, b'\0').unwrap();
+        assert_eq!(
+            matches,
+            vec![(1, "begin\nfoo\nend\0".to_string()), (3, "begin\nfoo\0".to_string())]
+        );
+
+        let matches = find_lines(Cursor::new(&text), &re, true, b'\0').unwrap();
+        assert_eq!(matches, vec![(2, "begin\nbar\nend\0".to_string())]);
+    }
+
+    #[test]
+    fn test_body() {
+        assert_eq!(body("foo\n"), "foo");
+        assert_eq!(body("foo\r\n"), "foo");
+        assert_eq!(body("foo"), "foo");
+        assert_eq!(body("foo\0"), "foo");
+        // 取り除く終端は1つだけ
+        assert_eq!(body("foo\n\n"), "foo\n");
+        assert_eq!(body("a\nb\r\n\0"), "a\nb\r\n");
+    }
+
+    #[test]
+    fn test_find_lines_crlf() {
+        // CRLF の行でも `$` が行末にマッチする。出力する行には CRLF が残る
+        let text = b"foo\r\nfoo bar\r\nbar foo\r\nfoo";
+        let re = Regex::new("foo$").unwrap();
+        let matches = find_lines(Cursor::new(&text), &re, false, b'\n').unwrap();
+        assert_eq!(
+            matches,
+            vec![(1, "foo\r\n".to_string()), (3, "bar foo\r\n".to_string()), (4, "foo".to_string())]
+        );
+
+        // -x と同じ、行全体へのマッチ
+        let re = Regex::new("^(?:foo)$").unwrap();
+        let matches = find_lines(Cursor::new(&text), &re, false, b'\n').unwrap();
+        assert_eq!(matches, vec![(1, "foo\r\n".to_string()), (4, "foo".to_string())]);
+        assert!(has_match(Cursor::new(b"foo\r\n"), &re, false, b'\n').unwrap());
+    }
+
+    #[test]
+    fn test_highlight() {
+        let red = Style::new().reverse().fg(Colour::Red);
+        let re = Regex::new("o").unwrap();
+        assert_eq!(
+            highlight("foo bar\n", &re, red),
+            "f\u{1b}[7;31mo\u{1b}[0m\u{1b}[7;31mo\u{1b}[0m bar\n"
+        );
+
+        // マッチしなければそのまま
+        assert_eq!(highlight("bar\r\n", &re, red), "bar\r\n");
+
+        // 改行や空のマッチには色を付けない
+        let re = Regex::new("\\s*").unwrap();
+        assert_eq!(highlight("a b\n", &re, red), "a\u{1b}[7;31m \u{1b}[0mb\n");
+
+        // 色は指定したものになる
+        let green = Style::new().reverse().fg(Colour::Green);
+        let re = Regex::new("b").unwrap();
+        assert_eq!(highlight("abc\n", &re, green), "a\u{1b}[7;32mb\u{1b}[0mc\n");
+    }
+
+    #[test]
+    fn test_only_matching() {
+        let re = Regex::new(r"id=(\d+)").unwrap();
+        assert_eq!(only_matching("id=1 id=23\n", &re, 0), vec!["id=1", "id=23"]);
+        assert_eq!(only_matching("id=1 id=23\n", &re, 1), vec!["1", "23"]);
+        assert_eq!(only_matching("no ids here\n", &re, 1), Vec::<&str>::new());
+
+        // 存在しないグループは飛ばす
+        assert_eq!(only_matching("id=1\n", &re, 2), Vec::<&str>::new());
+
+        // 参加しなかったグループも飛ばす
+        let re = Regex::new(r"(a)|(b)").unwrap();
+        assert_eq!(only_matching("ab", &re, 2), vec!["b"]);
+
+        // 空のマッチは出さない
+        let re = Regex::new(r"x*").unwrap();
+        assert_eq!(only_matching("axxb", &re, 0), vec!["xx"]);
+    }
+
+    #[test]
+    fn test_replace_line() {
+        let re = Regex::new(r"id=(\d+)").unwrap();
+        assert_eq!(replace_line("user id=12 logged in\n", &re, "found $1"), "user found 12 logged in\n");
+        assert_eq!(replace_line("id=7 and id=345\r\n", &re, "[$1]"), "[7] and [345]\r\n");
+        // 名前付きグループと、$$ による '$' そのもの
+        let re = Regex::new(r"(?P<key>\w+)=(?P<val>\d+)").unwrap();
+        assert_eq!(replace_line("a=1 b=2", &re, "${val}$$${key}"), "1$a 2$b");
+        // 改行にはマッチさせない
+        let re = Regex::new(r"\s*$").unwrap();
+        assert_eq!(replace_line("foo  \n", &re, "!"), "foo!\n");
+    }
+
+    #[test]
+    fn test_json_record() {
+        let re = Regex::new("o").unwrap();
+        let record = json_record("a.txt", &Output::Match(3, "foo bar\n".to_string()), &re, false).unwrap();
+        assert_eq!(
+            record.to_string(),
+            r#"{"line":"foo bar","line_number":3,"path":"a.txt","submatches":[{"end":2,"match":"o","start":1},{"end":3,"match":"o","start":2}],"type":"match"}"#
+        );
+
+        // 文脈の行と -v でマッチした行には部分マッチがない
+        let record = json_record("a.txt", &Output::Context(4, "foo\n".to_string()), &re, false).unwrap();
+        assert_eq!(record["type"], "context");
+        assert_eq!(record["submatches"].as_array().unwrap().len(), 0);
+        let record = json_record("a.txt", &Output::Match(1, "bar\n".to_string()), &re, true).unwrap();
+        assert_eq!(record["submatches"].as_array().unwrap().len(), 0);
+
+        assert_eq!(json_record("a.txt", &Output::Separator, &re, false), None);
+    }
+
+    #[test]
+    fn test_has_match() {
+        let text = b"Lorem\nIpsum\r\nDOLOR";
+        let re = Regex::new("or").unwrap();
+        assert!(has_match(Cursor::new(&text), &re, false, b'\n').unwrap());
+        assert!(has_match(Cursor::new(&text), &re, true, b'\n').unwrap());
+
+        let re = Regex::new("xyz").unwrap();
+        assert!(!has_match(Cursor::new(&text), &re, false, b'\n').unwrap());
+        assert!(has_match(Cursor::new(&text), &re, true, b'\n').unwrap());
+        assert!(!has_match(Cursor::new(b""), &re, true, b'\n').unwrap());
+    }
+
+    #[test]
+    fn test_find_lines_with_context() {
+        let text = b"a\nb\nmatch1\nc\nd\ne\nmatch2\nf\n";
+        let re = Regex::new("match").unwrap();
+        let context = |n: usize, line: &str| Output::Context(n, format!("{}\n", line));
+        let matched = |n: usize, line: &str| Output::Match(n, format!("{}\n", line));
+
+        // 文脈なしならマッチした行だけ。離れていれば区切りを入れる
+        let lines = find_lines_with_context(Cursor::new(&text), &re, false, 0, 0, b'\n').unwrap();
+        assert_eq!(lines, vec![matched(3, "match1"), Output::Separator, matched(7, "match2")]);
+
+        // 後ろ 1 行
+        let lines = find_lines_with_context(Cursor::new(&text), &re, false, 0, 1, b'\n').unwrap();
+        assert_eq!(
+            lines,
+            vec![
+                matched(3, "match1"),
+                context(4, "c"),
+                Output::Separator,
+                matched(7, "match2"),
+                context(8, "f"),
+            ]
+        );
+
+        // 前 1 行、ファイル先頭を超えない
+        let lines = find_lines_with_context(Cursor::new(&text), &re, false, 5, 0, b'\n').unwrap();
+        assert_eq!(
+            lines,
+            vec![
+                context(1, "a"),
+                context(2, "b"),
+                matched(3, "match1"),
+                context(4, "c"),
+                context(5, "d"),
+                context(6, "e"),
+                matched(7, "match2"),
+            ]
+        );
+
+        // 前後 2 行で窓がつながるときは区切りを入れない
+        let lines = find_lines_with_context(Cursor::new(&text), &re, false, 2, 2, b'\n').unwrap();
+        assert_eq!(lines.len(), 8);
+        assert!(!lines.contains(&Output::Separator));
+    }
+
+    #[test]
+    fn test_find_lines_with_context_overlap() {
+        let re = Regex::new("match").unwrap();
+        let context = |n: usize, line: &str| Output::Context(n, format!("{}\n", line));
+        let matched = |n: usize, line: &str| Output::Match(n, format!("{}\n", line));
+
+        // 2 行目と 4 行目のマッチで窓が重なる (3 行目は両方の窓に入る) ときは、各行を1回だけ出す
+        let text = b"l1\nmatch2\nl3\nmatch4\nl5\nl6\n";
+        let lines = find_lines_with_context(Cursor::new(&text), &re, false, 1, 1, b'\n').unwrap();
+        assert_eq!(
+            lines,
+            vec![
+                context(1, "l1"),
+                matched(2, "match2"),
+                context(3, "l3"),
+                matched(4, "match4"),
+                context(5, "l5"),
+            ]
+        );
+
+        // 窓が接しているだけのときも区切りは入れない
+        let text = b"l1\nmatch2\nl3\nl4\nmatch5\nl6\n";
+        let lines = find_lines_with_context(Cursor::new(&text), &re, false, 1, 1, b'\n').unwrap();
+        assert_eq!(lines.len(), 6);
+        assert!(!lines.contains(&Output::Separator));
+
+        // 2 行目と 8 行目のように離れていれば、2 つのグループの間に区切りを入れる
+        let text = b"l1\nmatch2\nl3\nl4\nl5\nl6\nl7\nmatch8\nl9\n";
+        let lines = find_lines_with_context(Cursor::new(&text), &re, false, 1, 1, b'\n').unwrap();
+        assert_eq!(
+            lines,
+            vec![
+                context(1, "l1"),
+                matched(2, "match2"),
+                context(3, "l3"),
+                Output::Separator,
+                context(7, "l7"),
+                matched(8, "match8"),
+                context(9, "l9"),
+            ]
+        );
+    }
+}
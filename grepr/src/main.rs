@@ -1,6 +1,11 @@
 fn main() {
-    if let Err(e) = grepr::get_config().and_then(grepr::run) {
-        eprintln!("{}", e);
-        std::process::exit(1);
+    match grepr::get_config().and_then(grepr::run) {
+        Ok(true) => {}
+        // マッチする行がなければ grep と同じく 1 で終了する
+        Ok(false) => std::process::exit(1),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
     }
 }
@@ -42,7 +42,7 @@ fn dies_no_args() -> TestResult {
 #[test]
 fn dies_bad_pattern() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&["*foo", FOX])
+        .args(["*foo", FOX])
         .assert()
         .failure()
         .stderr(predicate::str::contains("Invalid pattern \"*foo\""));
@@ -55,7 +55,7 @@ fn warns_bad_file() -> TestResult {
     let bad = gen_bad_file();
     let expected = format!("{}: .* [(]os error 2[)]", bad);
     Command::cargo_bin(PRG)?
-        .args(&["foo", &bad])
+        .args(["foo", &bad])
         .assert()
         .stderr(predicate::str::is_match(expected)?);
     Ok(())
@@ -72,7 +72,7 @@ fn run(args: &[&str], expected_file: &str) -> TestResult {
         expected_file
     };
 
-    let expected = fs::read_to_string(&expected_file)?;
+    let expected = fs::read_to_string(expected_file)?;
 
     Command::cargo_bin(PRG)?
         .args(args)
@@ -234,7 +234,7 @@ fn warns_dir_not_recursive() -> TestResult {
     let stdout = "tests/inputs/fox.txt:\
         The quick brown fox jumps over the lazy dog.";
     Command::cargo_bin(PRG)?
-        .args(&["fox", INPUTS_DIR, FOX])
+        .args(["fox", INPUTS_DIR, FOX])
         .assert()
         .stderr(predicate::str::contains("tests/inputs is a directory"))
         .stdout(predicate::str::contains(stdout));
@@ -271,9 +271,946 @@ fn stdin_insensitive_count() -> TestResult {
     let expected = fs::read_to_string(expected_file)?;
 
     Command::cargo_bin(PRG)?
-        .args(&["-ci", "the"])
+        .args(["-ci", "the"])
         .write_stdin(input)
         .assert()
         .stdout(expected);
     Ok(())
 }
+
+// --------------------------------------------------
+#[test]
+fn bustle_line_number() -> TestResult {
+    run(
+        &["-n", "The", BUSTLE],
+        "tests/expected/bustle.txt.the.capitalized.n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn multiple_files_insensitive_line_number() -> TestResult {
+    run(
+        &["-i", "--line-number", "the", BUSTLE, FOX],
+        "tests/expected/all.the.lowercase.insensitive.n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn sensitive_count_line_number() -> TestResult {
+    run(
+        &["--count", "-n", "The", BUSTLE],
+        "tests/expected/bustle.txt.the.capitalized.count",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_after_context() -> TestResult {
+    run(
+        &["-A", "1", "The", BUSTLE],
+        "tests/expected/bustle.txt.the.capitalized-A1",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_before_context() -> TestResult {
+    run(
+        &["--before-context", "1", "The", BUSTLE],
+        "tests/expected/bustle.txt.the.capitalized-B1",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_context() -> TestResult {
+    run(
+        &["-C", "1", "The", BUSTLE],
+        "tests/expected/bustle.txt.the.capitalized-C1",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_context_line_number() -> TestResult {
+    run(
+        &["-n", "-C", "1", "The", BUSTLE],
+        "tests/expected/bustle.txt.the.capitalized.n-C1",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn sensitive_count_ignores_context() -> TestResult {
+    run(
+        &["--count", "-C", "1", "The", BUSTLE],
+        "tests/expected/bustle.txt.the.capitalized.count",
+    )
+}
+
+// --------------------------------------------------
+// find_files がパスを並べ替えて重複を除くので、再帰検索でもパスの順に出力される
+// Windows の区切り文字だけ揃えて、出力の順番どおりに比較する
+fn run_lines(args: &[&str], expected: &[&str]) -> TestResult {
+    let cmd = Command::cargo_bin(PRG)?.args(args).assert().success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let lines: Vec<String> =
+        stdout.lines().map(|line| line.replace('\\', "/")).collect();
+    assert_eq!(lines, expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn recursive_files_with_matches() -> TestResult {
+    run_lines(
+        &["-r", "-l", "the", INPUTS_DIR],
+        &[
+            "tests/inputs/bustle.txt",
+            "tests/inputs/fox.txt",
+            "tests/inputs/nobody.txt",
+        ],
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn recursive_files_without_match() -> TestResult {
+    run_lines(
+        &["-r", "--files-without-match", "the", INPUTS_DIR],
+        &["tests/inputs/empty.txt"],
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn recursive_files_with_matches_invert() -> TestResult {
+    run_lines(
+        &["-r", "-l", "-v", "dog", INPUTS_DIR],
+        &["tests/inputs/bustle.txt", "tests/inputs/nobody.txt"],
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn single_file_with_matches() -> TestResult {
+    run_lines(&["-l", "The", BUSTLE], &["tests/inputs/bustle.txt"])
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_files_with_matches_and_count() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-l", "-c", "the", BUSTLE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_files_with_and_without_match() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-l", "-L", "the", BUSTLE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn fixed_strings_dot() -> TestResult {
+    run(&["-F", ".", BUSTLE], "tests/expected/bustle.txt.dot.fixed")
+}
+
+// --------------------------------------------------
+#[test]
+fn multiple_regexps() -> TestResult {
+    run(
+        &["-e", "fox", "--regexp", "Nobody", FOX, NOBODY],
+        "tests/expected/fox_nobody.e.e",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn multiple_regexps_insensitive() -> TestResult {
+    run(
+        &["-i", "-e", "FOX", "-e", "nobody", FOX, NOBODY],
+        "tests/expected/fox_nobody.e.e",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_regexps() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-e", "foo", "-e", "*x", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid pattern \"foo|*x\""));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn color_always() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--color=always", "fox", FOX, NOBODY])
+        .assert()
+        .success()
+        .stdout(
+            "tests/inputs/fox.txt:The quick brown \u{1b}[7;31mfox\u{1b}[0m jumps over the lazy dog.\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn color_always_match_color_green() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--color=always", "--match-color=green", "fox", FOX])
+        .assert()
+        .success()
+        .stdout("The quick brown \u{1b}[7;32mfox\u{1b}[0m jumps over the lazy dog.\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn color_always_match_color_only_matching() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--color=always", "--match-color", "blue", "-o", "fox", FOX])
+        .assert()
+        .success()
+        .stdout("\u{1b}[7;34mfox\u{1b}[0m\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn match_color_without_color() -> TestResult {
+    // --color がなければ色は付けない
+    run(
+        &["--match-color=yellow", "The", BUSTLE],
+        "tests/expected/bustle.txt.the.capitalized",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_match_color() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--color=always", "--match-color=purple", "fox", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid value 'purple'"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn color_auto_not_terminal() -> TestResult {
+    run(
+        &["--color", "The", BUSTLE],
+        "tests/expected/bustle.txt.the.capitalized",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn color_never() -> TestResult {
+    run(
+        &["--color=never", "The", BUSTLE],
+        "tests/expected/bustle.txt.the.capitalized",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn recursive_overlapping_paths() -> TestResult {
+    run(
+        &["-r", "-l", "the", INPUTS_DIR, FOX, BUSTLE],
+        "tests/expected/the.recursive.overlapping.l",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn same_file_twice_keeps_header() -> TestResult {
+    // 同じファイルを2回渡しても1回だけ検索し、複数の入力としてファイル名を付ける
+    Command::cargo_bin(PRG)?
+        .args(["fox", FOX, FOX])
+        .assert()
+        .success()
+        .stdout(format!("{}:The quick brown fox jumps over the lazy dog.\n", FOX));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn quiet_match() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-q", "fox", FOX])
+        .assert()
+        .success()
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn quiet_no_match() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--quiet", "cat", FOX, BUSTLE])
+        .assert()
+        .code(1)
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn quiet_match_in_later_file() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-q", "-i", "nobody", EMPTY, FOX, NOBODY])
+        .assert()
+        .success()
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn no_match_exits_one() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["cat", FOX])
+        .assert()
+        .code(1)
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn files_without_match_exit_code() -> TestResult {
+    // -L では、マッチしなかったファイルを出力すれば 0 で終了する
+    Command::cargo_bin(PRG)?
+        .args(["-L", "fox", FOX, BUSTLE])
+        .assert()
+        .success()
+        .stdout(format!("{}\n", BUSTLE));
+
+    // すべてのファイルがマッチして何も出力しなければ 1 で終了する
+    Command::cargo_bin(PRG)?
+        .args(["-L", "the", FOX, BUSTLE])
+        .assert()
+        .code(1)
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn pattern_file() -> TestResult {
+    run(
+        &["-f", "tests/patterns/fox_nobody.txt", FOX, NOBODY],
+        "tests/expected/fox_nobody.e.e",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn pattern_file_and_regexp() -> TestResult {
+    run(
+        &["-e", "fox", "--file", "tests/patterns/empty.txt", "-e", "Nobody", FOX, NOBODY],
+        "tests/expected/fox_nobody.e.e",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn pattern_file_empty_line_matches_all() -> TestResult {
+    run(
+        &["-f", "tests/patterns/fox_empty.txt", FOX, NOBODY],
+        "tests/expected/fox_nobody.f.empty_line",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn empty_pattern_file_matches_nothing() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-f", "tests/patterns/empty.txt", FOX])
+        .assert()
+        .code(1)
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_pattern_file() -> TestResult {
+    let bad = gen_bad_file();
+    Command::cargo_bin(PRG)?
+        .args(["-f", &bad, FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::is_match(format!("{}: .* [(]os error 2[)]", bad))?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn group_separator_between_files() -> TestResult {
+    run(&["-A1", "fox|Nobody", FOX, NOBODY], "tests/expected/fox_nobody.A1")
+}
+
+// --------------------------------------------------
+#[test]
+fn group_separator_default() -> TestResult {
+    run(
+        &["-C1", "-i", "the|nobody", BUSTLE, NOBODY],
+        "tests/expected/bustle_nobody.the_nobody.i-C1",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn group_separator_custom() -> TestResult {
+    run(
+        &["-C1", "-i", "--group-separator", "~~", "the|nobody", BUSTLE, NOBODY],
+        "tests/expected/bustle_nobody.the_nobody.i-C1.sep",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn group_separator_between_files_without_context() -> TestResult {
+    // 文脈を指定しなくても、--group-separator を指定すればファイルの間に区切りを出す
+    Command::cargo_bin(PRG)?
+        .args(["--group-separator=XX", "fox|Nobody", FOX, NOBODY])
+        .assert()
+        .success()
+        .stdout(format!(
+            "{fox}:The quick brown fox jumps over the lazy dog.\nXX\n\
+             {nobody}:I'm Nobody! Who are you?\r\n\
+             {nobody}:Are you—Nobody—too?\r\n",
+            fox = FOX,
+            nobody = NOBODY,
+        ));
+
+    // 何も指定しなければ区切りは出さない
+    Command::cargo_bin(PRG)?
+        .args(["fox|Nobody", FOX, NOBODY])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--").not());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn group_separator_after_context_zero() -> TestResult {
+    // -A 0 でも離れたマッチの間には区切りを出す
+    Command::cargo_bin(PRG)?
+        .args(["-A", "0", "a"])
+        .write_stdin("a1\nb\na2\na3\n")
+        .assert()
+        .success()
+        .stdout("a1\n--\na2\na3\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn no_group_separator() -> TestResult {
+    run(
+        &["-C1", "-i", "--no-group-separator", "the|nobody", BUSTLE, NOBODY],
+        "tests/expected/bustle_nobody.the_nobody.i-C1.nosep",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn count_matches() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--count-matches", "-i", "the", BUSTLE])
+        .assert()
+        .success()
+        .stdout("4\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn count_matches_multiple_files() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--count-matches", "Nobody|fox", FOX, NOBODY])
+        .assert()
+        .success()
+        .stdout("tests/inputs/fox.txt:1\ntests/inputs/nobody.txt:2\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn count_matches_invert_counts_lines() -> TestResult {
+    // -v のときはマッチしない行の数を数える (-c -v と同じ)
+    let expected = "8\n";
+    Command::cargo_bin(PRG)?
+        .args(["--count-matches", "-v", "the", BUSTLE])
+        .assert()
+        .success()
+        .stdout(expected);
+    Command::cargo_bin(PRG)?
+        .args(["-c", "-v", "the", BUSTLE])
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_count_matches_and_count() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--count-matches", "-c", "the", BUSTLE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+const IDS: &str = "user id=12 logged in\nno id on this line\nid=7 and id=345\n";
+
+// --------------------------------------------------
+#[test]
+fn only_matching() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-o", r"id=\d+"])
+        .write_stdin(IDS)
+        .assert()
+        .success()
+        .stdout("id=12\nid=7\nid=345\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn only_matching_group() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-o", "--group", "1", r"id=(\d+)"])
+        .write_stdin(IDS)
+        .assert()
+        .success()
+        .stdout("12\n7\n345\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn only_matching_group_line_number() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-o", "-n", "--group", "1", r"id=(\d+)"])
+        .write_stdin(IDS)
+        .assert()
+        .success()
+        .stdout("1:12\n3:7\n3:345\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn only_matching_missing_group() -> TestResult {
+    // 存在しないグループは出力されないが、マッチはしているので成功する
+    Command::cargo_bin(PRG)?
+        .args(["-o", "--group", "2", r"id=(\d+)"])
+        .write_stdin(IDS)
+        .assert()
+        .success()
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn replace_capture_group() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([r"id=(\d+)", "--replace", "found $1"])
+        .write_stdin(IDS)
+        .assert()
+        .success()
+        .stdout("user found 12 logged in\nfound 7 and found 345\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn replace_with_context_and_line_number() -> TestResult {
+    // 文脈の行は置き換えない
+    Command::cargo_bin(PRG)?
+        .args(["-n", "-A", "1", r"user id=(\d+)", "--replace", "<${1}>"])
+        .write_stdin(IDS)
+        .assert()
+        .success()
+        .stdout("1:<12> logged in\n2-no id on this line\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_replace_and_only_matching() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-o", r"id=(\d+)", "--replace", "$1"])
+        .write_stdin(IDS)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_replace_and_count() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-c", r"id=(\d+)", "--replace", "$1"])
+        .write_stdin(IDS)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_group_without_only_matching() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--group", "1", r"id=(\d+)"])
+        .write_stdin(IDS)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--only-matching"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_only_matching_and_invert() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-o", "-v", "the", BUSTLE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn stats_recursive() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--stats", "-r", "-i", "the", INPUTS_DIR])
+        .assert()
+        .success()
+        .stderr("4 files searched, 3 files matched, 7 matching lines\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn stats_no_match() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--stats", "-r", "nothing like this", INPUTS_DIR])
+        .assert()
+        .failure()
+        .code(1)
+        .stderr("4 files searched, 0 files matched, 0 matching lines\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn stats_ignores_context_lines() -> TestResult {
+    // 文脈の行は数えない
+    Command::cargo_bin(PRG)?
+        .args(["--stats", "-C", "1", "dog", FOX, BUSTLE])
+        .assert()
+        .success()
+        .stderr("2 files searched, 1 files matched, 1 matching lines\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_stats_and_files_with_matches() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--stats", "-l", "the", BUSTLE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn context_overlapping_windows_merge() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "-C", "1", "match"])
+        .write_stdin("l1\nmatch2\nl3\nmatch4\nl5\nl6\n")
+        .assert()
+        .success()
+        .stdout("1-l1\n2:match2\n3-l3\n4:match4\n5-l5\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn context_distant_windows_separated() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "-C", "1", "match"])
+        .write_stdin("l1\nmatch2\nl3\nl4\nl5\nl6\nl7\nmatch8\nl9\n")
+        .assert()
+        .success()
+        .stdout("1-l1\n2:match2\n3-l3\n--\n7-l7\n8:match8\n9-l9\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+// .gitignore を含むディレクトリを一時ディレクトリに作る
+// リポジトリの .gitignore に *.log などが含まれるため、fixture はコミットせず実行時に作る
+fn gen_ignore_tree() -> std::io::Result<String> {
+    let name: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(7)
+        .map(char::from)
+        .collect();
+    let dir = std::env::temp_dir().join(format!("grepr-ignore-{}", name));
+    fs::create_dir_all(dir.join("build"))?;
+    fs::write(dir.join(".gitignore"), "build/\n*.log\n")?;
+    fs::write(dir.join("keep.txt"), "dog\n")?;
+    fs::write(dir.join("debug.log"), "dog\n")?;
+    fs::write(dir.join("build/out.txt"), "dog\n")?;
+    Ok(dir.to_string_lossy().into_owned())
+}
+
+fn listed_files(args: &[&str]) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let output = Command::cargo_bin(PRG)?.args(args).output()?;
+    assert!(output.status.success());
+    let mut files: Vec<String> = String::from_utf8(output.stdout)?
+        .lines()
+        .map(|l| l.to_string())
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+#[test]
+fn recursive_skips_gitignored() -> TestResult {
+    let dir = gen_ignore_tree()?;
+    let files = listed_files(&["-r", "-l", "dog", &dir])?;
+    fs::remove_dir_all(&dir)?;
+    assert_eq!(files, vec![format!("{}/keep.txt", dir)]);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn recursive_no_ignore() -> TestResult {
+    let dir = gen_ignore_tree()?;
+    let files = listed_files(&["-r", "-l", "--no-ignore", "dog", &dir])?;
+    fs::remove_dir_all(&dir)?;
+    assert_eq!(
+        files,
+        vec![
+            format!("{}/build/out.txt", dir),
+            format!("{}/debug.log", dir),
+            format!("{}/keep.txt", dir),
+        ]
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+const RECORDS: &str = "[a]\nname=foo\nport=80\n\0[b]\nname=bar\nport=8080\n\0[c]\nname=foo\n";
+
+// --------------------------------------------------
+#[test]
+fn null_data_multiline_pattern() -> TestResult {
+    // 2行にわたるパターンで、NUL で区切ったレコードごとに調べる
+    Command::cargo_bin(PRG)?
+        .args(["-z", r"name=foo\nport"])
+        .write_stdin(RECORDS)
+        .assert()
+        .success()
+        .stdout("[a]\nname=foo\nport=80\n\0");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn null_data_dot_matches_newline() -> TestResult {
+    // '.' は改行にもマッチする。最後のレコードにも NUL を付けて出す
+    Command::cargo_bin(PRG)?
+        .args(["--null-data", r"\[[ac]\].*foo"])
+        .write_stdin(RECORDS)
+        .assert()
+        .success()
+        .stdout("[a]\nname=foo\nport=80\n\0[c]\nname=foo\n\0");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn null_data_count() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-z", "-c", "name=foo"])
+        .write_stdin(RECORDS)
+        .assert()
+        .success()
+        .stdout("2\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn json_submatches() -> TestResult {
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["--json", "-i", "the", BUSTLE])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let expected = fs::read_to_string(BUSTLE)?;
+    let expected: Vec<_> = expected.lines().enumerate().filter(|(_, line)| line.to_lowercase().contains("the")).collect();
+
+    // 1行に1つずつ、正しい JSON のオブジェクトが並ぶ
+    let records = stdout
+        .lines()
+        .map(serde_json::from_str::<serde_json::Value>)
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(records.len(), expected.len());
+    for (record, (idx, line)) in records.iter().zip(expected) {
+        assert_eq!(record["type"], "match");
+        assert_eq!(record["path"], BUSTLE);
+        assert_eq!(record["line_number"], idx + 1);
+        assert_eq!(record["line"], line);
+        // バイト範囲で line を切り出すとマッチした文字列になる
+        let submatches = record["submatches"].as_array().unwrap();
+        assert!(!submatches.is_empty());
+        for sub in submatches {
+            let (start, end) = (sub["start"].as_u64().unwrap() as usize, sub["end"].as_u64().unwrap() as usize);
+            assert_eq!(&line[start..end], sub["match"]);
+            assert_eq!(line[start..end].to_lowercase(), "the");
+        }
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn json_with_context() -> TestResult {
+    // 文脈の行は type が context になり、部分マッチを持たない
+    Command::cargo_bin(PRG)?
+        .args(["--json", "-A", "1", "o"])
+        .write_stdin("a\nfoo\nb\nc\n")
+        .assert()
+        .success()
+        .stdout(concat!(
+            r#"{"line":"foo","line_number":2,"path":"(standard input)","#,
+            r#""submatches":[{"end":2,"match":"o","start":1},{"end":3,"match":"o","start":2}],"type":"match"}"#,
+            "\n",
+            r#"{"line":"b","line_number":3,"path":"(standard input)","submatches":[],"type":"context"}"#,
+            "\n",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_json_and_count() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--json", "--count", "fox", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+const CRLF: &str = "foo\r\nfoo bar\r\nbar foo\r\nbar\r\n";
+
+// --------------------------------------------------
+#[test]
+fn crlf_dollar_anchor() -> TestResult {
+    // `$` は CR の前の行末にマッチし、出力は CRLF のまま
+    Command::cargo_bin(PRG)?
+        .args(["-n", "foo$"])
+        .write_stdin(CRLF)
+        .assert()
+        .success()
+        .stdout("1:foo\r\n3:bar foo\r\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn crlf_line_regexp() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-x", "-e", "foo", "-e", "bar"])
+        .write_stdin(CRLF)
+        .assert()
+        .success()
+        .stdout("foo\r\nbar\r\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn crlf_line_regexp_strip_cr() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--line-regexp", "--strip-cr", "-A", "1", "foo"])
+        .write_stdin(CRLF)
+        .assert()
+        .success()
+        .stdout("foo\nfoo bar\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn crlf_only_matching_count() -> TestResult {
+    // -o の出力や --count-matches にも CR は含まれない
+    Command::cargo_bin(PRG)?
+        .args(["-o", r"\w+$"])
+        .write_stdin(CRLF)
+        .assert()
+        .success()
+        .stdout("foo\nbar\nfoo\nbar\n");
+    Command::cargo_bin(PRG)?
+        .args(["--count-matches", r"\s"])
+        .write_stdin(CRLF)
+        .assert()
+        .success()
+        .stdout("2\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn line_regexp_lf() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-x", "-i", "the quick brown fox jumps over the lazy dog.", FOX])
+        .assert()
+        .success()
+        .stdout("The quick brown fox jumps over the lazy dog.\n");
+    Command::cargo_bin(PRG)?
+        .args(["-x", "fox", FOX])
+        .assert()
+        .failure()
+        .stdout("");
+    Ok(())
+}
@@ -1,6 +1,11 @@
-use std::{error::Error, fs::File, io::{self, BufRead, BufReader, Read}};
+use std::{collections::VecDeque, error::Error, fs::File, io::{self, BufRead, BufReader, Read, Write}};
 use clap::Parser;
 
+#[path = "../../shared/count.rs"]
+mod count;
+
+use count::parse_count_with_suffix;
+
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
 #[derive(Parser, Debug)]
@@ -10,49 +15,91 @@ struct Args {
     #[arg(value_name = "FILE")]
     files: Option<Vec<String>>,
 
-    /// Number of lines
+    /// Number of lines (with a leading '-', all but the last LINES lines)
     #[arg(
         short('n'),
         long,
         default_value = "10",
         value_name = "LINES",
-        value_parser = clap::value_parser!(u64).range(1..)
+        allow_negative_numbers = true,
+        value_parser = parse_count
     )]
-    lines: u64,
+    lines: TakeValue,
 
-    /// Number of bytes
+    /// Number of bytes (with a leading '-', all but the last BYTES bytes; k, M, G suffixes allowed)
     #[arg(
         short('c'),
         long,
         value_name = "BYTES",
         conflicts_with("lines"),
-        value_parser = clap::value_parser!(u64).range(1..)
+        allow_negative_numbers = true,
+        value_parser = parse_byte_count
     )]
-    bytes: Option<u64>,
+    bytes: Option<TakeValue>,
+
+    /// Report how many files were processed to stderr
+    #[arg(long)]
+    summary: bool,
 }
 
 impl Args {
+    #[allow(clippy::wrong_self_convention)]
     fn to_config(self) -> MyResult<Config> {
         Ok(Config{
             files: self.files,
             print_mode: {
-                if let Some(byte_size) = self.bytes { PrintMode::ByteMode(byte_size as usize) }
-                else { PrintMode::LineMode(self.lines as usize) }
-            }
+                if let Some(byte_size) = self.bytes { PrintMode::ByteMode(byte_size) }
+                else { PrintMode::LineMode(self.lines) }
+            },
+            summary: self.summary,
         })
     }
 }
 
-#[derive(Debug)]
-enum PrintMode {
-    LineMode(usize),
-    ByteMode(usize),
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PrintMode {
+    LineMode(TakeValue),
+    ByteMode(TakeValue),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TakeValue {
+    // 先頭の N 個
+    First(usize),
+    // 末尾の N 個以外の全て
+    AllButLast(usize),
+}
+
+// "N" は先頭の N 個、 "-N" は末尾の N 個以外を表す
+// 先頭の N 個を取る場合は N >= 1 でなければならない
+fn parse_count(val: &str) -> Result<TakeValue, String> {
+    parse_take_value(val, |num| num.parse::<u64>().map_err(|e| e.to_string()))
+}
+
+// -c では tailr と同じく 1k, 2M のような接尾辞も使える
+fn parse_byte_count(val: &str) -> Result<TakeValue, String> {
+    parse_take_value(val, |num| parse_count_with_suffix(num).map_err(|e| e.to_string()))
+}
+
+fn parse_take_value(
+    val: &str,
+    parse_num: impl Fn(&str) -> Result<u64, String>,
+) -> Result<TakeValue, String> {
+    match val.strip_prefix('-') {
+        Some(num) => parse_num(num).map(|num| TakeValue::AllButLast(num as usize)),
+        None => match parse_num(val) {
+            Ok(0) => Err(format!("0 is not in 1..{}", u64::MAX)),
+            Ok(num) => Ok(TakeValue::First(num as usize)),
+            Err(e) => Err(e),
+        },
+    }
 }
 
 #[derive(Debug)]
 pub struct Config {
     files: Option<Vec<String>>,
     print_mode: PrintMode,
+    summary: bool,
 }
 
 pub fn get_config() -> MyResult<Config> {
@@ -60,64 +107,106 @@ pub fn get_config() -> MyResult<Config> {
 }
 
 
+// None または Some("-") なら stdin を読む
 fn open(input: Option<&str>) -> MyResult<Box<dyn BufRead>> {
     match input {
-        None => Ok(Box::new(BufReader::new(io::stdin()))),
+        None | Some("-") => Ok(Box::new(BufReader::new(io::stdin()))),
         Some(filename) => Ok(Box::new(BufReader::new(File::open(filename)?)))
     }   
 }
 
-fn print_head(filename: &str, mut buf_reader: Box<dyn BufRead>, print_mode: &PrintMode, not_head: bool, multi_file_flg: bool) {
+// reader の先頭から mode で選ばれた部分をバイト列のまま返す
+pub fn head(mut reader: impl BufRead, mode: PrintMode) -> MyResult<Vec<u8>> {
+    let mut head = Vec::new();
+
+    match mode {
+        PrintMode::LineMode(TakeValue::First(n)) => {
+            for _ in 0..n {
+                let bytes = reader.read_until(b'\n', &mut head)?;
+                if bytes == 0 {
+                    break;
+                }
+            }
+        },
+        PrintMode::LineMode(TakeValue::AllButLast(n)) => {
+            // 直近の n 行を保持しておき、あふれた行から取り出す
+            let mut last_lines = VecDeque::with_capacity(n + 1);
+            loop {
+                let mut line = Vec::new();
+                let bytes = reader.read_until(b'\n', &mut line)?;
+                if bytes == 0 {
+                    break;
+                }
+                last_lines.push_back(line);
+                if last_lines.len() > n {
+                    head.extend(last_lines.pop_front().unwrap());
+                }
+            }
+        },
+        PrintMode::ByteMode(TakeValue::First(n)) => {
+            // 1バイトずつではなくまとめて読み込む
+            reader.take(n as u64).read_to_end(&mut head)?;
+        },
+        PrintMode::ByteMode(TakeValue::AllButLast(n)) => {
+            reader.read_to_end(&mut head)?;
+            head.truncate(head.len().saturating_sub(n));
+        }
+    }
+
+    Ok(head)
+}
+
+fn print_head(filename: &str, buf_reader: Box<dyn BufRead>, print_mode: &PrintMode, not_head: bool, multi_file_flg: bool) -> MyResult<()> {
+    let mut out = io::stdout().lock();
+
     // 先頭のイテレータではない場合、空行を出力する
     if not_head {
-        println!("");
-    } 
+        writeln!(out)?;
+    }
 
     // 複数のfileが指定されていた場合は各ファイルの出力にヘッダーをつける
     if multi_file_flg {
-        println!("==> {} <==", filename);
+        writeln!(out, "==> {} <==", filename)?;
     }
 
+    let head = head(buf_reader, *print_mode)?;
     match print_mode {
-        PrintMode::LineMode(n) => {
-            let mut line = String::new();
-            for _ in 0..*n {
-                let bytes = buf_reader.read_line(&mut line)
-                    .expect("error while reading the file");
-                if bytes == 0 {
-                    break;
-                }
-                print!("{}", line);
-                line.clear();
-            }
-        },
-        PrintMode::ByteMode(n) => {
-            let bytes  = buf_reader.bytes().take(*n).collect::<Result<Vec<_>, _>>().expect("error while reading bytes");
-            print!("{}", String::from_utf8_lossy(&bytes))
-        }
+        // 行はバイト列のまま書き出すので、UTF-8 でないファイルもそのまま出力できる
+        PrintMode::LineMode(_) => out.write_all(&head)?,
+        PrintMode::ByteMode(_) => write!(out, "{}", String::from_utf8_lossy(&head))?,
     }
 
+    Ok(())
 }
 
 
 pub fn run(config: Config) -> MyResult<()> {
     // 少なくとも1つの処理でエラーが発生したか否か
     let mut err_flg = false;
+    // 開くことができたファイルの数と、指定されたファイルの数
+    let mut processed = 0;
+    let total;
 
     match config.files {
         None => {
+            total = 1;
             match open(None) {
                 Err(err) => {
                     eprintln!("stdin: {}", err);
                     err_flg = true;
                 },
                 Ok(buf_reader) => {
-                    print_head("not used", buf_reader, &config.print_mode, false, false);
+                    processed += 1;
+                    if let Err(err) = print_head("not used", buf_reader, &config.print_mode, false, false) {
+                        eprintln!("stdin: {}", err);
+                        err_flg = true;
+                    }
                 }
             }
 
         }
         Some(files) => {
+            total = files.len();
             // 入力ファイルの数が複数あるか
             let multi_file_flg = files.len() > 1;
 
@@ -130,7 +219,11 @@ pub fn run(config: Config) -> MyResult<()> {
                         true
                     },
                     Ok(buf_reader) => {
-                        print_head(filename, buf_reader, &config.print_mode, not_head, multi_file_flg);
+                        processed += 1;
+                        if let Err(err) = print_head(filename, buf_reader, &config.print_mode, not_head, multi_file_flg) {
+                            eprintln!("{}: {}", filename, err);
+                            err_flg = true;
+                        }
                         true
                     }
                 }
@@ -138,9 +231,76 @@ pub fn run(config: Config) -> MyResult<()> {
         }
     }
 
+    if config.summary {
+        eprintln!("headr: processed {} of {} files", processed, total);
+    }
+
     if err_flg {
         Err(From::from("少なくとも1つのファイルに対してエラーが発生しました"))
     } else {
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::{head, parse_byte_count, parse_count, PrintMode::*, TakeValue::*};
+
+    #[test]
+    fn test_parse_count() {
+        assert_eq!(parse_count("3"), Ok(First(3)));
+        assert_eq!(parse_count("-3"), Ok(AllButLast(3)));
+        assert_eq!(parse_count("-0"), Ok(AllButLast(0)));
+
+        assert_eq!(parse_count("0"), Err(format!("0 is not in 1..{}", u64::MAX)));
+        assert_eq!(parse_count("foo"), Err("invalid digit found in string".to_string()));
+        assert_eq!(parse_count("-foo"), Err("invalid digit found in string".to_string()));
+        assert!(parse_count("--3").is_err());
+        // 行数には接尾辞は使えない
+        assert!(parse_count("1k").is_err());
+    }
+
+    #[test]
+    fn test_parse_byte_count() {
+        assert_eq!(parse_byte_count("3"), Ok(First(3)));
+        assert_eq!(parse_byte_count("1k"), Ok(First(1024)));
+        assert_eq!(parse_byte_count("-2K"), Ok(AllButLast(2048)));
+        assert_eq!(parse_byte_count("1M"), Ok(First(1 << 20)));
+
+        assert_eq!(parse_byte_count("0k"), Err(format!("0 is not in 1..{}", u64::MAX)));
+        assert_eq!(parse_byte_count("foo"), Err("invalid digit found in string".to_string()));
+        assert_eq!(parse_byte_count(&"9".repeat(40)), Err("count too large".to_string()));
+    }
+
+    #[test]
+    fn test_head_lines() {
+        let text = "one\ntwo\r\nthree";
+        assert_eq!(head(Cursor::new(text), LineMode(First(1))).unwrap(), b"one\n");
+        assert_eq!(head(Cursor::new(text), LineMode(First(2))).unwrap(), b"one\ntwo\r\n");
+        // 行数が足りなければ全体を返す。最後の行に改行がなくてもそのまま
+        assert_eq!(head(Cursor::new(text), LineMode(First(10))).unwrap(), text.as_bytes());
+        assert_eq!(head(Cursor::new(""), LineMode(First(10))).unwrap(), b"");
+
+        assert_eq!(head(Cursor::new(text), LineMode(AllButLast(1))).unwrap(), b"one\ntwo\r\n");
+        assert_eq!(head(Cursor::new(text), LineMode(AllButLast(0))).unwrap(), text.as_bytes());
+        assert_eq!(head(Cursor::new(text), LineMode(AllButLast(10))).unwrap(), b"");
+
+        // UTF-8 でない行もそのまま返す
+        assert_eq!(head(Cursor::new(b"\xff\xfe\nx\n"), LineMode(First(1))).unwrap(), b"\xff\xfe\n");
+    }
+
+    #[test]
+    fn test_head_bytes() {
+        let text = "ábc";
+        // マルチバイト文字の途中で切れてもバイト列のまま返す
+        assert_eq!(head(Cursor::new(text), ByteMode(First(1))).unwrap(), b"\xc3");
+        assert_eq!(head(Cursor::new(text), ByteMode(First(3))).unwrap(), "áb".as_bytes());
+        assert_eq!(head(Cursor::new(text), ByteMode(First(100))).unwrap(), text.as_bytes());
+
+        assert_eq!(head(Cursor::new(text), ByteMode(AllButLast(1))).unwrap(), "áb".as_bytes());
+        assert_eq!(head(Cursor::new(text), ByteMode(AllButLast(0))).unwrap(), text.as_bytes());
+        assert_eq!(head(Cursor::new(text), ByteMode(AllButLast(100))).unwrap(), b"");
+    }
+}
@@ -15,6 +15,9 @@ const ONE: &str = "./tests/inputs/one.txt";
 const TWO: &str = "./tests/inputs/two.txt";
 const THREE: &str = "./tests/inputs/three.txt";
 const TEN: &str = "./tests/inputs/ten.txt";
+const FIVE: &str = "./tests/inputs/five.txt";
+const TEN_BYTES: &str = "./tests/inputs/ten_bytes.txt";
+const INVALID_UTF8: &str = "./tests/inputs/invalid-utf8.txt";
 
 // --------------------------------------------------
 fn random_string() -> String {
@@ -44,7 +47,7 @@ fn dies_bad_bytes() -> TestResult {
         '--bytes <BYTES>': invalid digit found in string"
     );
     Command::cargo_bin(PRG)?
-        .args(&["-c", &bad, EMPTY])
+        .args(["-c", &bad, EMPTY])
         .assert()
         .failure()
         .stderr(predicate::str::contains(expected));
@@ -61,7 +64,7 @@ fn dies_bad_lines() -> TestResult {
         '--lines <LINES>': invalid digit found in string"
     );
     Command::cargo_bin(PRG)?
-        .args(&["-n", &bad, EMPTY])
+        .args(["-n", &bad, EMPTY])
         .assert()
         .failure()
         .stderr(predicate::str::contains(expected));
@@ -76,7 +79,7 @@ fn dies_bytes_and_lines() -> TestResult {
                used with '--bytes <BYTES>'";
 
     Command::cargo_bin(PRG)?
-        .args(&["-n", "1", "-c", "2"])
+        .args(["-n", "1", "-c", "2"])
         .assert()
         .failure()
         .stderr(predicate::str::contains(msg));
@@ -109,7 +112,7 @@ fn run(args: &[&str], expected_file: &str) -> TestResult {
         .args(args)
         .assert()
         .success()
-        .stdout(predicate::eq(&expected.as_bytes() as &[u8]));
+        .stdout(predicate::eq(expected.as_bytes() as &[u8]));
 
     Ok(())
 }
@@ -131,7 +134,7 @@ fn run_stdin(
         .write_stdin(input)
         .args(args)
         .assert()
-        .stdout(predicate::eq(&expected.as_bytes() as &[u8]));
+        .stdout(predicate::eq(expected.as_bytes() as &[u8]));
 
     Ok(())
 }
@@ -425,3 +428,121 @@ fn multiple_files_c4() -> TestResult {
         "tests/expected/all.c4.out",
     )
 }
+
+// --------------------------------------------------
+#[test]
+fn summary() -> TestResult {
+    let bad = gen_bad_file();
+    Command::cargo_bin(PRG)?
+        .args(["--summary", ONE, &bad])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("headr: processed 1 of 2 files"));
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn no_summary_by_default() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([ONE, TWO])
+        .assert()
+        .success()
+        .stderr("");
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn five_all_but_last_n2() -> TestResult {
+    run(&[FIVE, "-n", "-2"], "tests/expected/five.txt.n-2.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn five_all_but_last_n20() -> TestResult {
+    run(&[FIVE, "-n", "-20"], "tests/expected/five.txt.n-20.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn five_all_but_last_c6() -> TestResult {
+    run(&[FIVE, "-c", "-6"], "tests/expected/five.txt.c-6.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn reports_read_error() -> TestResult {
+    // ディレクトリは開けるが読み込みに失敗する
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", ONE])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("==> ./tests/inputs/one.txt <==\nÖne line, four words.\n"))
+        .stderr(predicate::str::is_match("tests/inputs: .* [(]os error 21[)]")?);
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn stdin_between_files() -> TestResult {
+    let input = fs::read_to_string(TWO)?;
+    let expected = fs::read_to_string("tests/expected/one_stdin_three.n2.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["-n", "2", ONE, "-", THREE])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(expected);
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn ten_bytes_c100() -> TestResult {
+    run(&[TEN_BYTES, "-c", "100"], "tests/expected/ten_bytes.txt.c100.out")
+}
+
+// --------------------------------------------------
+// 行単位で読むときは UTF-8 でないバイトもそのまま出力する
+#[test]
+fn invalid_utf8_n2() -> TestResult {
+    let expected = fs::read("tests/expected/invalid-utf8.txt.n2.out")?;
+    Command::cargo_bin(PRG)?
+        .args([INVALID_UTF8, "-n", "2"])
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn invalid_utf8_all_but_last_n1() -> TestResult {
+    let expected = fs::read("tests/expected/invalid-utf8.txt.n2.out")?;
+    Command::cargo_bin(PRG)?
+        .args([INVALID_UTF8, "-n", "-1"])
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn c1k_stdin() -> TestResult {
+    let input = "0123456789".repeat(200);
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["-c", "1k"])
+        .write_stdin(input.clone())
+        .assert()
+        .success();
+    let stdout = &cmd.get_output().stdout;
+    assert_eq!(stdout.len(), 1024);
+    assert_eq!(stdout.as_slice(), &input.as_bytes()[..1024]);
+    Ok(())
+}
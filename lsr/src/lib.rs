@@ -1,10 +1,20 @@
 mod owner;
 
-use std::{error::Error, fs, os::unix::fs::MetadataExt, path::PathBuf};
-
-use chrono::{DateTime, Local};
-use clap::Parser;
+use std::{
+    cmp::Reverse,
+    error::Error,
+    fs::{self, Metadata},
+    io,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use chrono::{DateTime, Local, SecondsFormat};
+use clap::{Parser, ValueEnum};
+use glob::Pattern;
 use owner::Owner;
+use serde_json::{json, Value};
 use tabular::{Row, Table};
 use users::{get_group_by_gid, get_user_by_uid};
 
@@ -25,18 +35,90 @@ struct Args {
     /// Show all files
     #[arg(short = 'a', long = "all")]
     show_hidden: bool,
+
+    /// Omit the group column in long listing
+    #[arg(short = 'G', long = "no-group")]
+    no_group: bool,
+
+    /// Sort order of the entries
+    #[arg(long, value_name = "KEY", value_enum, default_value = "name")]
+    sort: SortKey,
+
+    /// Append an indicator (one of /*@) to entries
+    #[arg(short = 'F', long)]
+    classify: bool,
+
+    /// Print the allocated size of each entry, in blocks
+    #[arg(short, long)]
+    size: bool,
+
+    /// List directories before files (each group keeps the --sort order)
+    #[arg(long)]
+    group_directories_first: bool,
+
+    /// Show information for the file a symbolic link points to, not the link itself
+    #[arg(short = 'L', long)]
+    dereference: bool,
+
+    /// Print the long listing as a JSON array
+    #[arg(long, conflicts_with_all = ["no_group", "classify", "size"])]
+    json: bool,
+
+    /// Do not list entries whose name matches the glob PATTERN (repeatable)
+    #[arg(short = 'I', long = "ignore", value_name = "PATTERN")]
+    ignore: Vec<Pattern>,
+}
+
+// ------------------------------------------------------------------------------------------------
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SortKey {
+    /// Keep the order of the arguments (and the directory read order)
+    None,
+    Name,
+    /// Largest first
+    Size,
+    /// Newest first
+    Time,
 }
 
 // ------------------------------------------------------------------------------------------------
 pub fn run() -> MyResult<()> {
     let config = Args::parse();
-    let paths = find_files(&config.paths, config.show_hidden)?;
+    let mut paths = find_files(&config.paths, config.show_hidden, &config.ignore)?;
+    sort_paths(&mut paths, config.sort, config.dereference);
+    if config.group_directories_first {
+        group_directories_first(&mut paths);
+    }
 
-    if config.long {
-        println!("{}", format_output(&paths)?)
+    if config.json {
+        println!("{}", format_json(&paths, config.dereference)?)
+    } else if config.long {
+        println!(
+            "{}",
+            format_output(
+                &paths,
+                config.no_group,
+                config.classify,
+                config.size,
+                config.dereference
+            )?
+        )
     } else {
+        // 長い形式と同じく、リンク切れなどで metadata が取れないエントリはエラーを出して飛ばす
         for path in paths {
-            println!("{}", path.display());
+            let metadata = match metadata(&path, config.dereference) {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    eprintln!("{}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            let name = display_name(&path, config.classify, config.dereference);
+            if config.size {
+                println!("{} {}", blocks(&metadata), name);
+            } else {
+                println!("{}", name);
+            }
         }
     }
 
@@ -46,7 +128,7 @@ pub fn run() -> MyResult<()> {
 // ------------------------------------------------------------------------------------------------
 /// paths の各エントリに対し、file ならそのまま、dir ならその要素のリストを取得して、それらを flat　にして返す関数
 /// 存在しなかったり取得できない場合はその都度エラー出力がなされ、処理は止まらない
-fn find_files(paths: &[String], show_hidden: bool) -> MyResult<Vec<PathBuf>> {
+fn find_files(paths: &[String], show_hidden: bool, ignore: &[Pattern]) -> MyResult<Vec<PathBuf>> {
     let mut pathbufs = Vec::new();
 
     for path in paths.iter() {
@@ -57,7 +139,7 @@ fn find_files(paths: &[String], show_hidden: bool) -> MyResult<Vec<PathBuf>> {
                     pathbufs.push(PathBuf::from(path));
                 } else if metadate.is_dir() {
                     // dir の場合
-                    add_entries(&mut pathbufs, path);
+                    add_entries(&mut pathbufs, path, ignore);
                 } else {
                     // おそらく symlink?
                     eprintln!("skip: path is not file or dir. Is this symlink? {}", path);
@@ -84,12 +166,22 @@ fn find_files(paths: &[String], show_hidden: bool) -> MyResult<Vec<PathBuf>> {
 }
 
 // ------------------------------------------------------------------------------------------------
-fn add_entries(pathbufs: &mut Vec<PathBuf>, path: &String) {
+/// --ignore のパターンのどれかに名前がマッチするエントリは捨てる
+/// ls と同じく、引数で直接指定されたファイルには --ignore を適用しない
+fn add_entries(pathbufs: &mut Vec<PathBuf>, path: &String, ignore: &[Pattern]) {
     match fs::read_dir(path) {
         Ok(entries) => {
             for entry in entries {
                 match entry {
-                    Ok(entry) => pathbufs.push(PathBuf::from(entry.path())),
+                    Ok(entry) => {
+                        let ignored = entry
+                            .file_name()
+                            .to_str()
+                            .is_some_and(|name| ignore.iter().any(|pat| pat.matches(name)));
+                        if !ignored {
+                            pathbufs.push(entry.path());
+                        }
+                    }
                     Err(e) => {
                         eprintln!("err & skip: エントリの取得\n{:#?}", e)
                     }
@@ -103,13 +195,54 @@ fn add_entries(pathbufs: &mut Vec<PathBuf>, path: &String) {
 }
 
 // ------------------------------------------------------------------------------------------------
-fn format_output(paths: &[PathBuf]) -> MyResult<String> {
-    //               1   2     3     4     5     6     7     8
-    let fmt = "{:<}{:<}  {:>}  {:<}  {:<}  {:>}  {:<}  {:<}";
-    let mut table = Table::new(fmt);
+/// size, time で並べるときも、値が同じものは名前順にする
+/// metadata が取得できないものは 0 や UNIX_EPOCH として扱う
+/// dereference のときだけ symlink の指す先の大きさ・時刻で並べる
+fn sort_paths(paths: &mut [PathBuf], key: SortKey, dereference: bool) {
+    if key == SortKey::None {
+        return;
+    }
 
-    for path in paths {
-        let metadata = fs::metadata(path)?;
+    paths.sort();
+    match key {
+        SortKey::Size => paths.sort_by_cached_key(|path| {
+            Reverse(metadata(path, dereference).map(|m| m.len()).unwrap_or(0))
+        }),
+        SortKey::Time => paths.sort_by_cached_key(|path| {
+            Reverse(
+                metadata(path, dereference)
+                    .and_then(|m| m.modified())
+                    .unwrap_or(SystemTime::UNIX_EPOCH),
+            )
+        }),
+        SortKey::None | SortKey::Name => {}
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+/// ディレクトリ（ディレクトリへの symlink も含む）を前に集める
+/// 安定ソートなので、それぞれの中では sort_paths で並べた順が保たれる
+fn group_directories_first(paths: &mut [PathBuf]) {
+    paths.sort_by_cached_key(|path| !path.is_dir());
+}
+
+// ------------------------------------------------------------------------------------------------
+/// 長い形式で表示する 1 エントリ分の情報
+struct LongEntry {
+    /// "l"（symlink）、"d"（dir）、"-"（それ以外）
+    kind: &'static str,
+    mode: String,
+    nlink: u64,
+    /// 名前が引けない場合は uid / gid をそのまま使う
+    user: String,
+    group: String,
+    size: u64,
+    blocks: u64,
+    modified: DateTime<Local>,
+}
+
+impl LongEntry {
+    fn new(metadata: &Metadata) -> MyResult<Self> {
         let uid = metadata.uid();
         let user = get_user_by_uid(uid)
             .map(|u| u.name().to_string_lossy().into_owned())
@@ -120,24 +253,162 @@ fn format_output(paths: &[PathBuf]) -> MyResult<String> {
             .map(|g| g.name().to_string_lossy().into_owned())
             .unwrap_or_else(|| gid.to_string());
 
-        let modified: DateTime<Local> = DateTime::from(metadata.modified()?);
-
-        table.add_row(
-            Row::new()
-                .with_cell(if path.is_dir() { "d" } else { "-" })
-                .with_cell(format_mode(metadata.mode()))
-                .with_cell(metadata.nlink())
-                .with_cell(user)
-                .with_cell(group)
-                .with_cell(metadata.len())
-                .with_cell(modified.format("%b %d %y %H:%M"))
-                .with_cell(path.display()),
-        );
+        let file_type = metadata.file_type();
+        let kind = if file_type.is_symlink() {
+            "l"
+        } else if file_type.is_dir() {
+            "d"
+        } else {
+            "-"
+        };
+
+        Ok(LongEntry {
+            kind,
+            mode: format_mode(metadata.mode()),
+            nlink: metadata.nlink(),
+            user,
+            group,
+            size: metadata.len(),
+            blocks: blocks(metadata),
+            modified: DateTime::from(metadata.modified()?),
+        })
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+/// format_output と同じ情報を、エントリごとのオブジェクトの配列として JSON にする
+/// type は "symlink"、"dir"、"file" のいずれか、modified は ISO 8601 (RFC 3339) 形式
+fn format_json(paths: &[PathBuf], dereference: bool) -> MyResult<String> {
+    let mut entries = Vec::new();
+
+    for path in paths {
+        // format_output と同じく、metadata が取れないエントリはエラーを出して飛ばす
+        let metadata = match metadata(path, dereference) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                continue;
+            }
+        };
+        let entry = LongEntry::new(&metadata)?;
+        let kind = match entry.kind {
+            "l" => "symlink",
+            "d" => "dir",
+            _ => "file",
+        };
+
+        entries.push(json!({
+            "name": path.display().to_string(),
+            "type": kind,
+            "mode": entry.mode,
+            "nlink": entry.nlink,
+            "user": entry.user,
+            "group": entry.group,
+            "size": entry.size,
+            "modified": entry.modified.to_rfc3339_opts(SecondsFormat::Secs, false),
+        }));
+    }
+
+    Ok(Value::Array(entries).to_string())
+}
+
+// ------------------------------------------------------------------------------------------------
+fn format_output(
+    paths: &[PathBuf],
+    no_group: bool,
+    classify: bool,
+    size: bool,
+    dereference: bool,
+) -> MyResult<String> {
+    // no_group のときは 5 番目（group）のセルを除く
+    let fmt = if no_group {
+        //   1   2     3     4     6     7     8
+        "{:<}{:<}  {:>}  {:<}  {:>}  {:<}  {:<}"
+    } else {
+        //   1   2     3     4     5     6     7     8
+        "{:<}{:<}  {:>}  {:<}  {:<}  {:>}  {:<}  {:<}"
+    };
+    // size のときは先頭にブロック数の列を足す
+    let fmt = if size {
+        format!("{{:>}} {}", fmt)
+    } else {
+        fmt.to_string()
+    };
+    let mut table = Table::new(&fmt);
+
+    for path in paths {
+        // リンク切れなどで metadata が取れないエントリはエラーを出して飛ばす
+        let metadata = match metadata(path, dereference) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                continue;
+            }
+        };
+        let entry = LongEntry::new(&metadata)?;
+
+        let mut row = Row::new();
+        if size {
+            row.add_cell(entry.blocks);
+        }
+        row = row
+            .with_cell(entry.kind)
+            .with_cell(entry.mode)
+            .with_cell(entry.nlink)
+            .with_cell(entry.user);
+        if !no_group {
+            row.add_cell(entry.group);
+        }
+        row.add_cell(entry.size);
+        row.add_cell(entry.modified.format("%b %d %y %H:%M"));
+        row.add_cell(display_name(path, classify, dereference));
+
+        table.add_row(row);
     }
 
     Ok(format!("{}", table))
 }
 
+// ------------------------------------------------------------------------------------------------
+/// dereference のときは symlink の指す先の、そうでなければ symlink 自体の metadata を返す
+fn metadata(path: &Path, dereference: bool) -> io::Result<Metadata> {
+    if dereference {
+        fs::metadata(path)
+    } else {
+        fs::symlink_metadata(path)
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+/// 割り当てられているブロック数。ls と同じく 1024 バイト単位で数える
+/// MetadataExt::blocks() は 512 バイト単位なので、2 で割って切り上げる
+fn blocks(metadata: &Metadata) -> u64 {
+    metadata.blocks().div_ceil(2)
+}
+
+// ------------------------------------------------------------------------------------------------
+/// 表示するエントリ名。classify のときは ls -F と同じく種類を表す記号を後ろにつける
+fn display_name(path: &Path, classify: bool, dereference: bool) -> String {
+    let name = path.display().to_string();
+    if classify {
+        name + indicator(path, dereference)
+    } else {
+        name
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+/// symlink は "@"、dir は "/"、所有者の実行権限があるファイルは "*"、それ以外は ""
+/// 行の他の列と同じ metadata で判定するので、dereference のときは symlink の指す先の種類になる
+fn indicator(path: &Path, dereference: bool) -> &'static str {
+    match metadata(path, dereference) {
+        Ok(metadata) if metadata.file_type().is_symlink() => "@",
+        Ok(metadata) if metadata.is_dir() => "/",
+        Ok(metadata) if metadata.mode() & 0o100 != 0 => "*",
+        _ => "",
+    }
+}
+
 // ------------------------------------------------------------------------------------------------
 /// 0o761のような8進数でファイルモードを指定すると、
 /// 「rwxr-x--x」のような文字列を返す
@@ -163,14 +434,20 @@ fn format_mode(mode: u32) -> String {
 // ------------------------------------------------------------------------------------------------
 #[cfg(test)]
 mod test {
-    use std::path::PathBuf;
+    use std::{path::PathBuf, slice};
 
-    use crate::{find_files, format_mode, format_output};
+    use glob::Pattern;
+    use std::path::Path;
+
+    use crate::{
+        blocks, find_files, format_json, format_mode, format_output, group_directories_first,
+        indicator, metadata, sort_paths, SortKey,
+    };
 
     #[test]
     fn test_find_files() {
         // ディレクトリにある隠しエントリ以外のエントリを検索する
-        let res = find_files(&["tests/inputs".to_string()], false);
+        let res = find_files(&["tests/inputs".to_string()], false, &[]);
         assert!(res.is_ok());
         let mut filenames: Vec<_> = res
             .unwrap()
@@ -189,7 +466,7 @@ mod test {
         );
 
         // 隠しファイルも含めて検索
-        let res = find_files(&["tests/inputs/.hidden".to_string()], true);
+        let res = find_files(&["tests/inputs/.hidden".to_string()], true, &[]);
         assert!(res.is_ok());
         let filenames: Vec<_> = res
             .unwrap()
@@ -205,6 +482,7 @@ mod test {
                 "tests/inputs/dir".to_string(),
             ],
             false,
+            &[],
         );
         assert!(res.is_ok());
         let mut filenames: Vec<_> = res
@@ -222,7 +500,7 @@ mod test {
     #[test]
     fn test_find_files_hidden() {
         // ディレクトリ内の全てのエントリを検索する
-        let res = find_files(&["tests/inputs".to_string()], true);
+        let res = find_files(&["tests/inputs".to_string()], true, &[]);
         assert!(res.is_ok());
         let mut filenames = res
             .unwrap()
@@ -242,18 +520,102 @@ mod test {
         )
     }
 
+    #[test]
+    fn test_find_files_ignore() {
+        // パターンにマッチする名前を除く。隠しエントリの扱いとも組み合わさる
+        let ignore = [Pattern::new("*.txt").unwrap(), Pattern::new("d?r").unwrap()];
+        let res = find_files(&["tests/inputs".to_string()], true, &ignore);
+        assert!(res.is_ok());
+        let filenames: Vec<_> = res
+            .unwrap()
+            .iter()
+            .map(|e| e.display().to_string())
+            .collect();
+        assert_eq!(filenames, ["tests/inputs/.hidden"]);
+
+        let res = find_files(&["tests/inputs".to_string()], false, &ignore);
+        assert!(res.unwrap().is_empty());
+    }
+
     #[test]
     fn test_format_mode() {
         assert_eq!(format_mode(0o755), "rwxr-xr-x");
         assert_eq!(format_mode(0o421), "r---w---x");
     }
 
+    #[test]
+    fn test_group_directories_first() {
+        // ディレクトリが前に来て、それぞれの中の順番は変わらない
+        let mut paths: Vec<_> = [
+            "tests/inputs/fox.txt",
+            "tests/classify/sub",
+            "tests/inputs/bustle.txt",
+            "tests/inputs/dir",
+            "tests/classify/link.txt",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+        group_directories_first(&mut paths);
+        assert_eq!(
+            paths,
+            [
+                "tests/classify/sub",
+                "tests/inputs/dir",
+                "tests/inputs/fox.txt",
+                "tests/inputs/bustle.txt",
+                "tests/classify/link.txt",
+            ]
+            .iter()
+            .map(PathBuf::from)
+            .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_indicator() {
+        assert_eq!(indicator(Path::new("tests/classify/sub"), false), "/");
+        assert_eq!(indicator(Path::new("tests/classify/run.sh"), false), "*");
+        assert_eq!(indicator(Path::new("tests/classify/link.txt"), false), "@");
+        assert_eq!(indicator(Path::new("tests/classify/plain.txt"), false), "");
+
+        // dereference のときは symlink の指す先で判定する
+        assert_eq!(indicator(Path::new("tests/classify/link.txt"), true), "");
+        assert_eq!(indicator(Path::new("tests/classify/sub"), true), "/");
+    }
+
+    #[test]
+    fn test_sort_paths_dereference() {
+        let dir = std::env::temp_dir().join(format!("lsr-sort-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a"), "x".repeat(100)).unwrap();
+        std::fs::write(dir.join("m"), "x".repeat(50)).unwrap();
+        std::os::unix::fs::symlink("a", dir.join("z")).unwrap();
+        let names = |paths: &[PathBuf]| -> Vec<String> {
+            paths
+                .iter()
+                .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+                .collect()
+        };
+
+        // symlink 自体の大きさ (リンク先の名前の長さ) で並べる
+        let mut paths = vec![dir.join("z"), dir.join("m"), dir.join("a")];
+        sort_paths(&mut paths, SortKey::Size, false);
+        assert_eq!(names(&paths), ["a", "m", "z"]);
+
+        // dereference のときはリンク先の大きさで並べる
+        sort_paths(&mut paths, SortKey::Size, true);
+        assert_eq!(names(&paths), ["a", "z", "m"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn test_format_output_one() {
         let bustle_path = "tests/inputs/bustle.txt";
         let bustle = PathBuf::from(bustle_path);
 
-        let res = format_output(&[bustle]);
+        let res = format_output(&[bustle], false, false, false, false);
         assert!(res.is_ok());
 
         let out = res.unwrap();
@@ -261,15 +623,45 @@ mod test {
         assert_eq!(lines.len(), 1);
 
         let line1 = lines.first().unwrap();
-        long_match(&line1, &bustle_path, "-rw-r--r--", Some("193"));
+        long_match(line1, bustle_path, "-rw-r--r--", Some("193"));
+    }
+
+    #[test]
+    fn test_format_json() {
+        let bustle_path = "tests/inputs/bustle.txt";
+        let out = format_json(&[PathBuf::from(bustle_path)], false).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&out).unwrap();
+
+        let entries = value.as_array().unwrap();
+        assert_eq!(entries.len(), 1);
+        let entry = entries[0].as_object().unwrap();
+        let keys: Vec<_> = entry.keys().map(String::as_str).collect();
+        assert_eq!(
+            keys,
+            ["name", "type", "mode", "nlink", "user", "group", "size", "modified"]
+        );
+        assert_eq!(entry["name"], bustle_path);
+        assert_eq!(entry["type"], "file");
+        assert_eq!(entry["mode"], "rw-r--r--");
+        assert_eq!(entry["size"], 193);
+        assert!(entry["nlink"].is_u64());
+        // 例: 2024-01-02T03:04:05+09:00
+        let modified = entry["modified"].as_str().unwrap();
+        assert!(chrono::DateTime::parse_from_rfc3339(modified).is_ok());
     }
 
     #[test]
     fn test_format_output_two() {
-        let res = format_output(&[
-            PathBuf::from("tests/inputs/dir"),
-            PathBuf::from("tests/inputs/empty.txt"),
-        ]);
+        let res = format_output(
+            &[
+                PathBuf::from("tests/inputs/dir"),
+                PathBuf::from("tests/inputs/empty.txt"),
+            ],
+            false,
+            false,
+            false,
+            false,
+        );
         assert!(res.is_ok());
 
         let out = res.unwrap();
@@ -278,14 +670,75 @@ mod test {
         assert_eq!(lines.len(), 2);
         let empty_line = lines.remove(0);
         long_match(
-            &empty_line,
+            empty_line,
             "tests/inputs/empty.txt",
             "-rw-r--r--",
             Some("0"),
         );
 
         let dir_line = lines.remove(0);
-        long_match(&dir_line, "tests/inputs/dir", "drwxr-xr-x", None);
+        long_match(dir_line, "tests/inputs/dir", "drwxr-xr-x", None);
+    }
+
+    #[test]
+    fn test_format_output_no_group() {
+        let bustle_path = "tests/inputs/bustle.txt";
+        let with_group =
+            format_output(&[PathBuf::from(bustle_path)], false, false, false, false).unwrap();
+        let without_group =
+            format_output(&[PathBuf::from(bustle_path)], true, false, false, false).unwrap();
+
+        let with_parts: Vec<_> = with_group.split_whitespace().collect();
+        let parts: Vec<_> = without_group.split_whitespace().collect();
+
+        // group の列だけがなくなり、user の列は残る
+        assert_eq!(parts.len(), with_parts.len() - 1);
+        assert_eq!(parts.get(2), with_parts.get(2));
+        assert_eq!(parts.get(3).unwrap(), &"193");
+        assert_eq!(parts.last().unwrap(), &bustle_path);
+    }
+
+    #[test]
+    fn test_format_output_size() {
+        let bustle_path = "tests/inputs/bustle.txt";
+        let bustle = PathBuf::from(bustle_path);
+        let without_size =
+            format_output(slice::from_ref(&bustle), false, false, false, false).unwrap();
+        let with_size = format_output(slice::from_ref(&bustle), false, false, true, false).unwrap();
+
+        // 先頭にブロック数の列が増え、残りの列はそのまま
+        let parts: Vec<_> = with_size.split_whitespace().collect();
+        let rest: Vec<_> = without_size.split_whitespace().collect();
+        assert_eq!(parts[1..], rest[..]);
+        assert_eq!(
+            parts[0],
+            blocks(&metadata(&bustle, false).unwrap()).to_string()
+        );
+    }
+
+    #[test]
+    fn test_blocks() {
+        // 空ファイルにはブロックが割り当てられない
+        let blocks_of = |path: &str| blocks(&metadata(Path::new(path), false).unwrap());
+        assert_eq!(blocks_of("tests/inputs/empty.txt"), 0);
+        assert!(blocks_of("tests/inputs/bustle.txt") > 0);
+    }
+
+    #[test]
+    fn test_format_output_dereference() {
+        let link = PathBuf::from("tests/classify/link.txt");
+
+        // symlink 自体: 種類は l、大きさはリンク先のパス "plain.txt" の長さ
+        let out = format_output(slice::from_ref(&link), false, false, false, false).unwrap();
+        let parts: Vec<_> = out.split_whitespace().collect();
+        assert_eq!(parts[0], "lrwxrwxrwx");
+        assert_eq!(parts[4], "9");
+
+        // -L ではリンク先の通常ファイル
+        let out = format_output(slice::from_ref(&link), false, false, false, true).unwrap();
+        let parts: Vec<_> = out.split_whitespace().collect();
+        assert_eq!(parts[0], "-rw-r--r--");
+        assert_eq!(parts[4], "6");
     }
 
     fn long_match(
@@ -295,9 +748,9 @@ mod test {
         expected_size: Option<&str>,
     ) {
         let parts: Vec<_> = line.split_whitespace().collect();
-        assert!(parts.len() > 0 && parts.len() <= 10);
+        assert!(!parts.is_empty() && parts.len() <= 10);
 
-        let perms = parts.get(0).unwrap();
+        let perms = parts.first().unwrap();
         assert_eq!(perms, &expected_perms);
 
         if let Some(size) = expected_size {
@@ -30,7 +30,7 @@ fn gen_bad_file() -> String {
 #[test]
 fn bad_file() -> TestResult {
     let bad = gen_bad_file();
-    let expected = format!("No such file or directory");
+    let expected = "No such file or directory".to_string();
     Command::cargo_bin(PRG)?
         .arg(&bad)
         .assert()
@@ -63,12 +63,12 @@ fn run_short(arg: &str) -> TestResult {
 // --------------------------------------------------
 fn run_long(filename: &str, permissions: &str, size: &str) -> TestResult {
     let cmd = Command::cargo_bin(PRG)?
-        .args(&["--long", filename])
+        .args(["--long", filename])
         .assert()
         .success();
     let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
     let parts: Vec<_> = stdout.split_whitespace().collect();
-    assert_eq!(parts.get(0).unwrap(), &permissions);
+    assert_eq!(parts.first().unwrap(), &permissions);
     assert_eq!(parts.get(4).unwrap(), &size);
     assert_eq!(parts.last().unwrap(), &filename);
     Ok(())
@@ -112,7 +112,7 @@ fn fox_long() -> TestResult {
 #[test]
 fn hidden() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&["-a", HIDDEN])
+        .args(["-a", HIDDEN])
         .assert()
         .success()
         .stdout(format!("{}\n", HIDDEN));
@@ -122,12 +122,12 @@ fn hidden() -> TestResult {
 #[test]
 fn hidden_long() -> TestResult {
     let cmd = Command::cargo_bin(PRG)?
-        .args(&["-a", "--long", HIDDEN])
+        .args(["-a", "--long", HIDDEN])
         .assert()
         .success();
     let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
     let parts: Vec<_> = stdout.split_whitespace().collect();
-    assert_eq!(parts.get(0).unwrap(), &"-rw-r--r--");
+    assert_eq!(parts.first().unwrap(), &"-rw-r--r--");
     assert_eq!(parts.get(4).unwrap(), &"0");
     assert_eq!(parts.last().unwrap(), &"tests/inputs/.hidden");
     Ok(())
@@ -140,7 +140,7 @@ fn dir_short(args: &[&str], expected: &[&str]) -> TestResult {
     let lines: Vec<&str> = stdout.split("\n").filter(|s| !s.is_empty()).collect();
     assert_eq!(lines.len(), expected.len());
     for filename in expected {
-        assert!(lines.contains(&filename));
+        assert!(lines.contains(filename));
     }
     Ok(())
 }
@@ -177,6 +177,35 @@ fn dir2() -> TestResult {
     dir_short(&["tests/inputs/dir"], &["tests/inputs/dir/spiders.txt"])
 }
 
+#[test]
+fn dir1_ignore_txt() -> TestResult {
+    dir_short(&["tests/inputs", "-I", "*.txt"], &["tests/inputs/dir"])
+}
+
+#[test]
+fn ignore_keeps_file_argument() -> TestResult {
+    // 引数で直接指定したファイルは --ignore にマッチしても表示する
+    dir_short(
+        &["-I", "*.txt", "tests/inputs/bustle.txt", "tests/inputs/dir"],
+        &["tests/inputs/bustle.txt"],
+    )
+}
+
+#[test]
+fn dir1_all_ignore_repeated() -> TestResult {
+    dir_short(
+        &[
+            "--all",
+            "--ignore",
+            "*.txt",
+            "--ignore",
+            ".*",
+            "tests/inputs",
+        ],
+        &["tests/inputs/dir"],
+    )
+}
+
 #[test]
 fn dir2_all() -> TestResult {
     dir_short(
@@ -195,11 +224,11 @@ fn dir_long(args: &[&str], expected: &[(&str, &str, &str)]) -> TestResult {
     let mut check = vec![];
     for line in lines {
         let parts: Vec<_> = line.split_whitespace().collect();
-        let path = parts.last().unwrap().clone();
-        let permissions = parts.get(0).unwrap().clone();
+        let path = *parts.last().unwrap();
+        let permissions = *parts.first().unwrap();
         let size = match permissions.chars().next() {
             Some('d') => "",
-            _ => parts.get(4).unwrap().clone(),
+            _ => *parts.get(4).unwrap(),
         };
         check.push((path, permissions, size));
     }
@@ -259,3 +288,249 @@ fn dir2_long_all() -> TestResult {
         ],
     )
 }
+
+// --------------------------------------------------
+fn run_sort(args: &[&str], expected: &[&str]) -> TestResult {
+    let expected: String = expected.iter().map(|path| format!("{}\n", path)).collect();
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn sort_none_keeps_argument_order() -> TestResult {
+    run_sort(&["--sort=none", FOX, EMPTY, BUSTLE], &[FOX, EMPTY, BUSTLE])
+}
+
+// --------------------------------------------------
+#[test]
+fn sort_name_by_default() -> TestResult {
+    run_sort(&[FOX, EMPTY, BUSTLE], &[BUSTLE, EMPTY, FOX])
+}
+
+// --------------------------------------------------
+#[test]
+fn group_directories_first() -> TestResult {
+    run_sort(
+        &["--group-directories-first", "tests/classify"],
+        &[
+            "tests/classify/sub",
+            "tests/classify/link.txt",
+            "tests/classify/plain.txt",
+            "tests/classify/run.sh",
+        ],
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn group_directories_first_sort_size() -> TestResult {
+    // ディレクトリより大きいファイルがあっても、ディレクトリが前に来る
+    // ファイルどうしは大きい順のまま
+    let dir = std::env::temp_dir().join(format!("lsr-group-{}", std::process::id()));
+    fs::create_dir_all(dir.join("sub"))?;
+    fs::write(dir.join("big.txt"), vec![b'x'; 100_000])?;
+    fs::write(dir.join("small.txt"), "x")?;
+    let path = |name: &str| dir.join(name).to_string_lossy().into_owned();
+
+    let res = run_sort(
+        &[
+            "--group-directories-first",
+            "--sort=size",
+            dir.to_str().unwrap(),
+        ],
+        &[&path("sub"), &path("big.txt"), &path("small.txt")],
+    );
+    fs::remove_dir_all(&dir)?;
+    res
+}
+
+// --------------------------------------------------
+#[test]
+fn sort_size() -> TestResult {
+    run_sort(
+        &["--sort", "size", FOX, EMPTY, BUSTLE],
+        &[BUSTLE, FOX, EMPTY],
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn classify() -> TestResult {
+    run_sort(
+        &["-F", "tests/classify"],
+        &[
+            "tests/classify/link.txt@",
+            "tests/classify/plain.txt",
+            "tests/classify/run.sh*",
+            "tests/classify/sub/",
+        ],
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn classify_long() -> TestResult {
+    let cmd = Command::cargo_bin(PRG)?
+        .args([
+            "--long",
+            "--classify",
+            "tests/classify/run.sh",
+            "tests/classify/sub",
+        ])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let names: Vec<_> = stdout
+        .lines()
+        .filter_map(|line| line.split_whitespace().last())
+        .collect();
+    assert_eq!(
+        names,
+        ["tests/classify/run.sh*", "tests/classify/sub/file.txt"]
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+fn block_counts(args: &[&str]) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let cmd = Command::cargo_bin(PRG)?.args(args).assert().success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    Ok(stdout
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .map(str::to_string)
+        .collect())
+}
+
+// --------------------------------------------------
+#[test]
+fn size_short() -> TestResult {
+    let blocks = block_counts(&["-s", BUSTLE, EMPTY, FOX])?;
+    assert_eq!(blocks.len(), 3);
+    assert!(blocks.iter().all(|b| b.parse::<u64>().is_ok()));
+    // 空ファイルは 0 ブロック
+    assert_eq!(blocks[1], "0");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn size_long() -> TestResult {
+    let blocks = block_counts(&["-s", "--long", "tests/inputs"])?;
+    assert_eq!(blocks.len(), 4);
+    assert!(blocks.iter().all(|b| b.parse::<u64>().is_ok()));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn json_dir() -> TestResult {
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["--json", "tests/inputs"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let value: serde_json::Value = serde_json::from_str(&stdout)?;
+    let entries = value.as_array().unwrap();
+    let names: Vec<_> = entries
+        .iter()
+        .map(|e| e["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "tests/inputs/bustle.txt",
+            "tests/inputs/dir",
+            "tests/inputs/empty.txt",
+            "tests/inputs/fox.txt",
+        ]
+    );
+    assert_eq!(entries[1]["type"], "dir");
+    assert_eq!(entries[2]["size"], 0);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_json_and_classify() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--json", "-F", "tests/inputs"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dereference_long() -> TestResult {
+    // link.txt -> plain.txt (6 バイト)
+    for (flag, expected) in [(None, "9"), (Some("-L"), "6")] {
+        let mut args = vec!["--long", "tests/classify/link.txt"];
+        args.extend(flag);
+        let cmd = Command::cargo_bin(PRG)?.args(&args).assert().success();
+        let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+        let parts: Vec<_> = stdout.split_whitespace().collect();
+        assert_eq!(parts.get(4), Some(&expected));
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dereference_skips_dangling_link() -> TestResult {
+    let dir = std::env::temp_dir().join(format!("lsr-dangling-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let target = dir.join("target.txt");
+    fs::write(&target, "hello\n")?;
+    std::os::unix::fs::symlink(dir.join("missing.txt"), dir.join("dangling.txt"))?;
+
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["--long", "--dereference", dir.to_str().unwrap()])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("dangling.txt"));
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let names: Vec<_> = stdout
+        .lines()
+        .filter_map(|line| line.split_whitespace().last())
+        .collect();
+    assert_eq!(names, [target.to_str().unwrap()]);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dereference_skips_dangling_link_short() -> TestResult {
+    let dir = std::env::temp_dir().join(format!("lsr-dangling-short-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let target = dir.join("target.txt");
+    fs::write(&target, "hello\n")?;
+    std::os::unix::fs::symlink(dir.join("missing.txt"), dir.join("dangling.txt"))?;
+
+    // 長い形式でなくても、-L のときはリンク切れをエラーにして飛ばす
+    for flags in [&[][..], &["-F"][..], &["-s"][..]] {
+        let cmd = Command::cargo_bin(PRG)?
+            .args(flags)
+            .args(["-L", dir.to_str().unwrap()])
+            .assert()
+            .success()
+            .stderr(predicate::str::contains("dangling.txt"));
+        let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+        let names: Vec<_> = stdout
+            .lines()
+            .filter_map(|line| line.split_whitespace().last())
+            .collect();
+        assert_eq!(names, [target.to_str().unwrap()]);
+    }
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
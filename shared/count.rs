@@ -0,0 +1,75 @@
+// headr と tailr の -c で共有する、接尾辞つきの数のパース
+// 各クレートから #[path] でモジュールとして読み込む
+
+use std::{
+    error::Error,
+    fmt,
+    num::{IntErrorKind, ParseIntError},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CountError {
+    Empty,
+    Invalid(ParseIntError),
+    TooLarge,
+}
+
+impl fmt::Display for CountError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CountError::Empty => write!(f, "empty count"),
+            CountError::Invalid(e) => write!(f, "{}", e),
+            CountError::TooLarge => write!(f, "count too large"),
+        }
+    }
+}
+
+impl Error for CountError {}
+
+// "N" の後ろに k (1024), M (1024^2), G (1024^3) をつけられる (大文字の K も k と同じ)
+// 符号の扱いはコマンドごとに違うので、符号を取り除いた後の部分を渡す
+pub fn parse_count_with_suffix(val: &str) -> Result<u64, CountError> {
+    let (num, unit) = match val.chars().last() {
+        Some('k' | 'K') => (&val[..val.len() - 1], 1 << 10),
+        Some('M') => (&val[..val.len() - 1], 1 << 20),
+        Some('G') => (&val[..val.len() - 1], 1 << 30),
+        _ => (val, 1),
+    };
+
+    let num: u64 = num.parse().map_err(|e: ParseIntError| match e.kind() {
+        IntErrorKind::Empty if val.is_empty() => CountError::Empty,
+        IntErrorKind::PosOverflow => CountError::TooLarge,
+        _ => CountError::Invalid(e),
+    })?;
+
+    num.checked_mul(unit).ok_or(CountError::TooLarge)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_count_with_suffix, CountError};
+
+    #[test]
+    fn test_parse_count_with_suffix() {
+        assert_eq!(parse_count_with_suffix("0"), Ok(0));
+        assert_eq!(parse_count_with_suffix("10"), Ok(10));
+        assert_eq!(parse_count_with_suffix("1k"), Ok(1024));
+        assert_eq!(parse_count_with_suffix("2K"), Ok(2048));
+        assert_eq!(parse_count_with_suffix("3M"), Ok(3 << 20));
+        assert_eq!(parse_count_with_suffix("1G"), Ok(1 << 30));
+
+        assert_eq!(parse_count_with_suffix(""), Err(CountError::Empty));
+        assert_eq!(parse_count_with_suffix(&"1".repeat(40)), Err(CountError::TooLarge));
+        assert_eq!(parse_count_with_suffix(&format!("{}G", u64::MAX)), Err(CountError::TooLarge));
+        assert_eq!(parse_count_with_suffix("17179869184G"), Err(CountError::TooLarge));
+
+        for bad in ["k", "foo", "1.5k", "1x", "-1", "1kk"] {
+            let res = parse_count_with_suffix(bad);
+            assert!(matches!(res, Err(CountError::Invalid(_))), "{}", bad);
+        }
+        assert_eq!(
+            parse_count_with_suffix("foo").unwrap_err().to_string(),
+            "invalid digit found in string"
+        );
+    }
+}
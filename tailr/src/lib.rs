@@ -2,15 +2,27 @@ use clap::Parser;
 use once_cell::sync::OnceCell;
 use regex::Regex;
 use std::{
+    collections::VecDeque,
     error::Error,
-    fs::File,
-    io::{BufRead, BufReader, Read, Seek},
+    fs::{self, File, Metadata},
+    num::IntErrorKind,
+    io::{self, BufRead, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write},
+    thread,
+    time::Duration,
 };
 use TakeValue::*;
 
+#[path = "../../shared/count.rs"]
+mod count;
+
+use count::{parse_count_with_suffix, CountError};
+
 // ------------------------------------------------------------------------------------------------
 static NUM_RE: OnceCell<Regex> = OnceCell::new();
 
+// -f でファイルの変更を確認する間隔
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(200);
+
 // ------------------------------------------------------------------------------------------------
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
@@ -18,34 +30,72 @@ type MyResult<T> = Result<T, Box<dyn Error>>;
 #[derive(Debug, Parser)]
 #[command(version, about)]
 struct Args {
-    /// Input file(s)
-    #[arg(required = true)]
+    /// Input file(s) ("-" is stdin)
+    #[arg(default_value = "-")]
     files: Vec<String>,
 
-    /// Number of lines
+    /// Number of lines (the last LINES lines; with a leading '+', start at line LINES)
     #[arg(value_name = "LINES", short = 'n', long, default_value = "10")]
     lines: String,
 
-    /// Number of bytes
+    /// Number of bytes (like --lines, with '+' to start at byte BYTES; k, M, G suffixes allowed)
     #[arg(value_name = "BYTES", short = 'c', long, conflicts_with("lines"))]
     bytes: Option<String>,
 
+    /// Output bytes starting at byte N, counting from 1 (same as -c +N)
+    #[arg(value_name = "N", long, conflicts_with_all(["lines", "bytes"]))]
+    bytes_from_start: Option<String>,
+
     /// Suppress headers
     #[arg(short, long)]
     quiet: bool,
+
+    /// Output appended data as the files grow
+    #[arg(short, long)]
+    follow: bool,
+
+    /// With -f, terminate after process PID dies (unix only)
+    #[arg(long, value_name = "PID", requires = "follow")]
+    pid: Option<i32>,
+
+    /// With -f, check whether a file has been replaced (e.g. by log rotation)
+    /// after it has not changed size for N checks, and follow the new file
+    #[arg(long, value_name = "N", default_value = "5", requires = "follow")]
+    max_unchanged_stats: usize,
 }
 
 // ------------------------------------------------------------------------------------------------
 impl Args {
+    #[allow(clippy::wrong_self_convention)]
     fn to_config(self) -> MyResult<Config> {
         let files = self.files;
         let quiet = self.quiet;
+        let follow = self.follow;
+        let pid = self.pid;
+        // kill(0, 0) や kill(-1, 0) はプロセスグループなどを対象にして常に成功し、follow が終わらなくなる
+        if let Some(pid) = pid.filter(|&pid| pid <= 0) {
+            return Err(From::from(format!("invalid PID -- {} (--pid)", pid)));
+        }
 
-        let tail_mode = if let Some(num) = self.bytes {
-            TailMode::Bytes(parse_num(&num).map_err(|e| format!("illegal byte count -- {}", e))?)
+        // --bytes-from-start N は -c +N と同じ。符号は付けられない
+        let tail_mode = if let Some(num) = self.bytes_from_start {
+            let byte_num = if num.starts_with(['+', '-']) {
+                Err(From::from(num.clone()))
+            } else {
+                parse_byte_num(&format!("+{}", num))
+            };
+            TailMode::Bytes(
+                byte_num.map_err(|e| format!("illegal byte count -- {} (--bytes-from-start)", e))?,
+            )
+        } else if let Some(num) = self.bytes {
+            TailMode::Bytes(
+                parse_byte_num(&num)
+                    .map_err(|e| format!("illegal byte count -- {} (-c/--bytes)", e))?,
+            )
         } else {
             TailMode::Lines(
-                parse_num(&self.lines).map_err(|e| format!("illegal line count -- {}", e))?,
+                parse_num(&self.lines)
+                    .map_err(|e| format!("illegal line count -- {} (-n/--lines)", e))?,
             )
         };
 
@@ -53,6 +103,9 @@ impl Args {
             files,
             quiet,
             tail_mode,
+            follow,
+            pid,
+            max_unchanged_stats: self.max_unchanged_stats,
         })
     }
 }
@@ -63,6 +116,11 @@ pub struct Config {
     files: Vec<String>,
     tail_mode: TailMode,
     quiet: bool,
+    follow: bool,
+    // follow モードで、このプロセスが終了したら監視をやめる
+    pid: Option<i32>,
+    // follow モードで、大きさが変わらないままこの回数確認したら、名前で stat し直して置き換えを調べる
+    max_unchanged_stats: usize,
 }
 
 // ------------------------------------------------------------------------------------------------
@@ -73,6 +131,9 @@ enum TailMode {
 }
 
 // ------------------------------------------------------------------------------------------------
+// parse_num / parse_byte_num は GNU tail と同じく符号で意味を区別する
+// "N" と "-N" は末尾の N 個で TakeNum(-N)、"+N" は N 番目から最後までで TakeNum(N) になる
+// "+0" は "+1" と同じく先頭から全部だが、"0"（何も出さない）と区別するため別の値にする
 #[derive(Debug, PartialEq, Clone, Copy)]
 enum TakeValue {
     PlusZero,
@@ -81,26 +142,55 @@ enum TakeValue {
 
 // ------------------------------------------------------------------------------------------------
 fn parse_num(val: &str) -> MyResult<TakeValue> {
+    if val.is_empty() {
+        return Err(From::from("empty count"));
+    }
+
     let num_re = NUM_RE.get_or_init(|| Regex::new(r"^([+-])?(\d+)$").unwrap());
 
     match num_re.captures(val) {
         Some(caps) => {
             let sign = caps.get(1).map_or("-", |m| m.as_str());
             let num = format!("{}{}", sign, caps.get(2).unwrap().as_str());
-            if let Ok(val) = num.parse() {
-                if sign == "+" && val == 0 {
-                    Ok(PlusZero)
-                } else {
-                    Ok(TakeNum(val))
-                }
-            } else {
-                Err(From::from(val))
+            match num.parse() {
+                Ok(0) if sign == "+" => Ok(PlusZero),
+                Ok(val) => Ok(TakeNum(val)),
+                // i64 に収まらない桁数の場合は値ではなく範囲外であることを伝える
+                Err(e) => match e.kind() {
+                    IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => {
+                        Err(From::from("count too large"))
+                    }
+                    _ => Err(From::from(val)),
+                },
             }
         }
         _ => Err(From::from(val)),
     }
 }
 
+// ------------------------------------------------------------------------------------------------
+// -c では headr と同じく 1k, 2M のような接尾辞も使える
+// 符号の扱いとエラーメッセージは parse_num と同じ
+fn parse_byte_num(val: &str) -> MyResult<TakeValue> {
+    let (sign, num) = match val.chars().next() {
+        Some(sign @ ('+' | '-')) => (sign, &val[1..]),
+        _ => ('-', val),
+    };
+    let num = parse_count_with_suffix(num).map_err(|e| match e {
+        CountError::Invalid(_) => val.to_string(),
+        e => e.to_string(),
+    })?;
+
+    match sign {
+        '+' if num == 0 => Ok(PlusZero),
+        '+' => i64::try_from(num).map(TakeNum).map_err(|_| From::from("count too large")),
+        _ => 0i64
+            .checked_sub_unsigned(num)
+            .map(TakeNum)
+            .ok_or_else(|| From::from("count too large")),
+    }
+}
+
 // ------------------------------------------------------------------------------------------------
 pub fn get_config() -> MyResult<Config> {
     Args::parse().to_config()
@@ -111,32 +201,241 @@ pub fn get_config() -> MyResult<Config> {
 // 複数ファイルがある場合、==> filename <== のヘッダーが、存在するファイルのみにつく
 // また、成功したファイルの2つ目以降はヘッダーの前に一行空行を入れる
 // quietモードの場合、ヘッダーだけでなく空行も出力しない
+// follow モードでは、最初の出力の後に開けたファイルへの追記を出力し続ける
+// ヘッダーも中身も同じ BufWriter を通して出力し、ファイルごとに flush して順番を保証する
 pub fn run(config: Config) -> MyResult<()> {
+    let mut out = BufWriter::new(io::stdout());
+    let is_multi = config.files.len() > 1;
+    let mut follower = Follower::new(is_multi && !config.quiet, config.max_unchanged_stats);
     for (idx, filename) in config.files.iter().enumerate() {
-        match File::open(filename) {
+        // "-" は stdin を表す (None)
+        let input = if filename == "-" {
+            Ok(None)
+        } else {
+            File::open(filename).map(Some)
+        };
+        match input {
             Err(err) => eprintln!("{}: {}", filename, err),
             Ok(file) => {
-                let is_multi = config.files.len() > 1;
                 if is_multi && !config.quiet {
-                    println!("{}==> {} <==", if idx > 0 { "\n" } else { "" }, filename);
+                    writeln!(out, "{}==> {} <==", if idx > 0 { "\n" } else { "" }, filename)?;
                 }
-                let (total_lines, total_bytes) = count_lines_bytes(filename)?;
-                let file = BufReader::new(file);
-                match config.tail_mode {
-                    TailMode::Lines(line_num) => print_lines(file, &line_num, total_lines)?,
-                    TailMode::Bytes(byte_num) => print_byte(file, &byte_num, total_bytes)?,
+                match file {
+                    Some(file) => {
+                        // 名前で開き直すと、その間の追記やファイルの置き換えを取りこぼすので、
+                        // 出力に使ったファイルを読み終えた位置から追いかける
+                        let mut reader = BufReader::new(file);
+                        let pos = print_tail(&mut reader, &config.tail_mode, &mut out)?;
+                        if config.follow {
+                            follower.add(filename, reader.into_inner(), pos)?;
+                        }
+                    }
+                    None => {
+                        // stdin は Seek できないので、一度すべてメモリに読み込む
+                        let mut buffer = Vec::new();
+                        io::stdin().read_to_end(&mut buffer)?;
+                        print_tail(Cursor::new(&buffer), &config.tail_mode, &mut out)?;
+                    }
                 }
+                out.flush()?;
             }
         }
     }
+
+    if config.follow && !follower.files.is_empty() {
+        follow(&mut follower, config.pid, &mut out)?;
+    }
     Ok(())
 }
 
 // ------------------------------------------------------------------------------------------------
-fn count_lines_bytes(filename: &str) -> MyResult<(usize, usize)> {
-    let file = File::open(filename)?;
-    let mut reader = BufReader::new(file);
+// pid が指定されていれば、そのプロセスが終了した後の最後の poll で抜ける
+// 指定されていなければ止まらない
+fn follow(follower: &mut Follower, pid: Option<i32>, out: &mut impl Write) -> MyResult<()> {
+    loop {
+        thread::sleep(FOLLOW_INTERVAL);
+        follower.poll(out)?;
+        if pid.is_some_and(|pid| !process_alive(pid)) {
+            return Ok(());
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// kill(pid, 0) でシグナルを送らずにプロセスの存在だけを確かめる
+// 権限がなくて送れない (EPERM) 場合も、プロセス自体は存在している
+#[cfg(unix)]
+fn process_alive(pid: i32) -> bool {
+    // SAFETY: シグナル 0 は何も送らず、存在と権限の確認だけを行う
+    let res = unsafe { libc::kill(pid, 0) };
+    res == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+// unix 以外では確かめる方法がないので、--pid は無視して監視を続ける
+#[cfg(not(unix))]
+fn process_alive(_pid: i32) -> bool {
+    true
+}
+
+// ------------------------------------------------------------------------------------------------
+// ファイルを同一視するための inode 番号
+#[cfg(unix)]
+fn inode(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.ino()
+}
+
+// unix 以外では inode がないので、置き換えは検出しない
+#[cfg(not(unix))]
+fn inode(_metadata: &Metadata) -> u64 {
+    0
+}
+
+// ------------------------------------------------------------------------------------------------
+// -f で監視しているファイル
+struct FollowedFile {
+    filename: String,
+    file: File,
+    // 開いているファイルの inode。名前で stat した結果と違えば置き換えられている
+    ino: u64,
+    // 次に読み込みを始める位置（ここまでは出力済み）
+    pos: u64,
+    // 大きさが変わらなかった poll が何回続いているか
+    unchanged: usize,
+}
+
+impl FollowedFile {
+    // 前回から増えた分を読む。切り詰められていたら先頭から読み直す
+    fn read_appended(&mut self) -> MyResult<Vec<u8>> {
+        let len = self.file.metadata()?.len();
+        if len < self.pos {
+            eprintln!("tailr: {}: file truncated", self.filename);
+            self.pos = 0;
+        }
+
+        let mut buffer = Vec::new();
+        if len > self.pos {
+            self.file.seek(SeekFrom::Start(self.pos))?;
+            (&self.file).take(len - self.pos).read_to_end(&mut buffer)?;
+            self.pos += buffer.len() as u64;
+        }
+        Ok(buffer)
+    }
+
+    // 名前で stat し直し、別のファイルに置き換えられていれば (logrotate など) 開き直して先頭から読む
+    // 名前のファイルがまだない (rename されただけ) ときは、そのまま元のファイルを監視する
+    fn reopen_if_replaced(&mut self) -> MyResult<bool> {
+        match fs::metadata(&self.filename) {
+            Ok(metadata) if inode(&metadata) != self.ino => {
+                eprintln!("tailr: {}: file replaced; following new file", self.filename);
+                self.file = File::open(&self.filename)?;
+                self.ino = inode(&self.file.metadata()?);
+                self.pos = 0;
+                self.unchanged = 0;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// ファイルの大きさを調べ、増えていればその分を出力する
+// 大きさが変わらないまま max_unchanged_stats 回確認したファイルは、置き換えられていないかも調べる
+// ループは run 側で回すので、poll を1回呼ぶと1回分だけ確認する
+struct Follower {
+    files: Vec<FollowedFile>,
+    show_headers: bool,
+    max_unchanged_stats: usize,
+    // 最後に出力したファイルの files でのインデックス
+    last_printed: Option<usize>,
+}
+
+impl Follower {
+    fn new(show_headers: bool, max_unchanged_stats: usize) -> Follower {
+        Follower {
+            files: Vec::new(),
+            show_headers,
+            max_unchanged_stats,
+            last_printed: None,
+        }
+    }
+
+    // 出力済みのファイルを現在の末尾から監視する
+    // file は最初の出力に使ったもの、pos はそこで読み終えた位置
+    fn add(&mut self, filename: &str, file: File, pos: u64) -> MyResult<()> {
+        let metadata = file.metadata()?;
+        self.last_printed = Some(self.files.len());
+        self.files.push(FollowedFile {
+            filename: filename.to_string(),
+            file,
+            ino: inode(&metadata),
+            pos,
+            unchanged: 0,
+        });
+        Ok(())
+    }
+
+    fn poll(&mut self, out: &mut impl Write) -> MyResult<()> {
+        for (idx, followed) in self.files.iter_mut().enumerate() {
+            // 置き換えられる前に元のファイルに追記された分も取りこぼさないよう、先に読んでおく
+            let mut buffer = followed.read_appended()?;
+            if buffer.is_empty() {
+                followed.unchanged += 1;
+            } else {
+                followed.unchanged = 0;
+            }
+            if followed.unchanged >= self.max_unchanged_stats && followed.reopen_if_replaced()? {
+                buffer.extend(followed.read_appended()?);
+            }
+            if buffer.is_empty() {
+                continue;
+            }
+
+            // 出力するファイルが切り替わったらヘッダーを出し直す
+            if self.show_headers && self.last_printed != Some(idx) {
+                writeln!(out, "\n==> {} <==", followed.filename)?;
+            }
+            self.last_printed = Some(idx);
+            write!(out, "{}", String::from_utf8_lossy(&buffer))?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// 行数・バイト数が必要なモードの時だけ、同じ reader で数えてから先頭に戻して出力する
+// 読み終えた位置を返す (follow はそこから追いかける)
+// 何も出力しない場合は読み進めないので、数えたところまでを読んだことにする
+fn print_tail<R>(mut reader: R, tail_mode: &TailMode, out: &mut impl Write) -> MyResult<u64>
+where
+    R: BufRead + Seek,
+{
+    let counted = match *tail_mode {
+        // 末尾から数える場合は全体の行数が不要なので、1回の読み込みで済ませる
+        TailMode::Lines(TakeNum(num)) if num < 0 => {
+            print_last_lines(&mut reader, num.unsigned_abs() as usize, out)?;
+            0
+        }
+        TailMode::Lines(line_num) => {
+            let (total_lines, total_bytes) = count_lines_bytes_in(&mut reader)?;
+            reader.seek(SeekFrom::Start(0))?;
+            print_lines(&mut reader, &line_num, total_lines, out)?;
+            total_bytes
+        }
+        TailMode::Bytes(byte_num) => {
+            let (_, total_bytes) = count_lines_bytes_in(&mut reader)?;
+            reader.seek(SeekFrom::Start(0))?;
+            print_byte(&mut reader, &byte_num, total_bytes, out)?;
+            total_bytes
+        }
+    };
+    Ok(reader.stream_position()?.max(counted as u64))
+}
 
+// ------------------------------------------------------------------------------------------------
+fn count_lines_bytes_in(mut reader: impl BufRead) -> MyResult<(usize, usize)> {
     let mut lines = 0;
     let mut bytes = 0;
     let mut buf = Vec::new();
@@ -154,7 +453,12 @@ fn count_lines_bytes(filename: &str) -> MyResult<(usize, usize)> {
     Ok((lines, bytes))
 }
 
-fn print_lines(mut file: impl BufRead, num_lines: &TakeValue, total_lines: usize) -> MyResult<()> {
+fn print_lines(
+    mut file: impl BufRead,
+    num_lines: &TakeValue,
+    total_lines: usize,
+    out: &mut impl Write,
+) -> MyResult<()> {
     if let Some(start) = get_start_index(num_lines, total_lines) {
         let mut line_num = 1;
         let mut buf = Vec::new();
@@ -164,7 +468,7 @@ fn print_lines(mut file: impl BufRead, num_lines: &TakeValue, total_lines: usize
                 break;
             }
             if line_num >= start {
-                print!("{}", String::from_utf8_lossy(&buf));
+                write!(out, "{}", String::from_utf8_lossy(&buf))?;
             }
             line_num += 1;
             buf.clear();
@@ -174,10 +478,40 @@ fn print_lines(mut file: impl BufRead, num_lines: &TakeValue, total_lines: usize
     Ok(())
 }
 
+// ------------------------------------------------------------------------------------------------
+// 末尾の num_lines 行を出力する
+// 直近の num_lines 行だけをリングバッファに保持するので、ファイルは1回しか読まない
+fn print_last_lines(mut file: impl BufRead, num_lines: usize, out: &mut impl Write) -> MyResult<()> {
+    let mut last_lines: VecDeque<Vec<u8>> = VecDeque::new();
+    loop {
+        let mut buf = Vec::new();
+        let bytes_read = file.read_until(b'\n', &mut buf)?;
+        if bytes_read == 0 {
+            break;
+        }
+        last_lines.push_back(buf);
+        if last_lines.len() > num_lines {
+            last_lines.pop_front();
+        }
+    }
+
+    for line in last_lines {
+        write!(out, "{}", String::from_utf8_lossy(&line))?;
+    }
+    Ok(())
+}
+
 // ------------------------------------------------------------------------------------------------
 // print_lineと同様に T を書かずに file: impl Read + Seek　としても良い
+// get_start_index の開始位置は 1-origin なので、seek する位置 (0-origin) はそれより 1 小さい
+// "+5" なら 5 バイト目 (オフセット 4) から最後まで出力する
 // Seek は多くのプログラミング言語で「カーソル」や「読み込みヘッド」と呼ばれるものをストリームの特定の位置に移動させることを意味する
-fn print_byte<T>(mut file: T, num_bytes: &TakeValue, total_bytes: usize) -> MyResult<()>
+fn print_byte<T>(
+    mut file: T,
+    num_bytes: &TakeValue,
+    total_bytes: usize,
+    out: &mut impl Write,
+) -> MyResult<()>
 where
     T: Read + Seek,
 {
@@ -186,7 +520,7 @@ where
         let mut buffer = Vec::new();
         file.read_to_end(&mut buffer)?;
         if !buffer.is_empty() {
-            print!("{}", String::from_utf8_lossy(&buffer));
+            write!(out, "{}", String::from_utf8_lossy(&buffer))?;
         }
     }
     Ok(())
@@ -224,7 +558,50 @@ fn get_start_index(take_val: &TakeValue, total: usize) -> Option<usize> {
 
 #[cfg(test)]
 mod tests {
-    use super::{count_lines_bytes, get_start_index, parse_num, TakeValue::*};
+    use super::{
+        count_lines_bytes_in, follow, get_start_index, parse_byte_num, parse_num, print_last_lines,
+        print_byte, print_lines, print_tail, process_alive, Follower, TailMode, TakeValue::*,
+    };
+    use std::{
+        env, fs,
+        fs::File,
+        io::{BufReader, Cursor, Read, Write},
+        path::PathBuf,
+        process,
+    };
+
+    // 読み込んだバイト数を数える
+    struct CountingReader<R> {
+        inner: R,
+        bytes_read: usize,
+    }
+
+    impl<R: Read> Read for CountingReader<R> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = self.inner.read(buf)?;
+            self.bytes_read += n;
+            Ok(n)
+        }
+    }
+
+    // テストごとに一時ファイルを作る
+    fn temp_file(name: &str, content: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("tailr-{}-{}", process::id(), name));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn append(path: &PathBuf, content: &str) {
+        let mut file = fs::OpenOptions::new().append(true).open(path).unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+    }
+
+    // ファイルの末尾から追いかける
+    fn add_at_end(follower: &mut Follower, path: &PathBuf) {
+        let file = File::open(path).unwrap();
+        let len = file.metadata().unwrap().len();
+        follower.add(path.to_str().unwrap(), file, len).unwrap();
+    }
 
     #[test]
     fn test_parse_num() {
@@ -276,15 +653,54 @@ mod tests {
         let res = parse_num("nyaa");
         assert!(res.is_err());
         assert_eq!(res.unwrap_err().to_string(), "nyaa");
+
+        // 空文字列
+        let res = parse_num("");
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().to_string(), "empty count");
+
+        // i64 に収まらない値
+        let big = "1".repeat(40);
+        let res = parse_num(&big);
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().to_string(), "count too large");
+
+        let res = parse_num(&format!("+{}", big));
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().to_string(), "count too large");
+    }
+
+    #[test]
+    fn test_parse_byte_num() {
+        // 接尾辞がなければ parse_num と同じ
+        for val in ["3", "+3", "-3", "0", "+0", "nyaa", "", "3.14"] {
+            assert_eq!(
+                parse_byte_num(val).map_err(|e| e.to_string()),
+                parse_num(val).map_err(|e| e.to_string())
+            );
+        }
+
+        assert_eq!(parse_byte_num("1k").unwrap(), TakeNum(-1024));
+        assert_eq!(parse_byte_num("+2K").unwrap(), TakeNum(2048));
+        assert_eq!(parse_byte_num("-1M").unwrap(), TakeNum(-(1 << 20)));
+        assert_eq!(parse_byte_num("+0k").unwrap(), PlusZero);
+        assert_eq!(parse_byte_num(&i64::MIN.to_string()).unwrap(), TakeNum(i64::MIN));
+
+        let res = parse_byte_num("1x");
+        assert_eq!(res.unwrap_err().to_string(), "1x");
+        let res = parse_byte_num(&format!("+{}", u64::MAX));
+        assert_eq!(res.unwrap_err().to_string(), "count too large");
+        let res = parse_byte_num("99999999999G");
+        assert_eq!(res.unwrap_err().to_string(), "count too large");
     }
 
     #[test]
     fn test_count_lines_bytes() {
-        let res = count_lines_bytes("tests/inputs/one.txt");
+        let res = count_lines_bytes_in(BufReader::new(File::open("tests/inputs/one.txt").unwrap()));
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), (1, 24));
 
-        let res = count_lines_bytes("tests/inputs/ten.txt");
+        let res = count_lines_bytes_in(BufReader::new(File::open("tests/inputs/ten.txt").unwrap()));
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), (10, 49));
     }
@@ -323,4 +739,236 @@ mod tests {
         // return 0 to print the whole file
         assert_eq!(get_start_index(&TakeNum(-20), 10), Some(1));
     }
+
+    #[test]
+    fn test_print_lines_from_start() {
+        // "+3" は末尾の3行ではなく、3行目から最後まで
+        let text = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+        let mut out = Vec::new();
+        print_lines(Cursor::new(text), &parse_num("+3").unwrap(), 10, &mut out).unwrap();
+        assert_eq!(String::from_utf8_lossy(&out), "3\n4\n5\n6\n7\n8\n9\n10\n");
+
+        let mut out = Vec::new();
+        print_lines(Cursor::new(text), &parse_num("3").unwrap(), 10, &mut out).unwrap();
+        assert_eq!(String::from_utf8_lossy(&out), "8\n9\n10\n");
+    }
+
+    #[test]
+    fn test_print_byte_from_start() {
+        // "+5" は 5 バイト目から最後まで (1-origin)
+        let text = "0123456789";
+        let mut out = Vec::new();
+        print_byte(Cursor::new(text), &parse_byte_num("+5").unwrap(), 10, &mut out).unwrap();
+        assert_eq!(String::from_utf8_lossy(&out), "456789");
+
+        // "-5" と "5" は末尾の 5 バイト
+        let mut out = Vec::new();
+        print_byte(Cursor::new(text), &parse_byte_num("-5").unwrap(), 10, &mut out).unwrap();
+        assert_eq!(String::from_utf8_lossy(&out), "56789");
+
+        // "+1" と "+0" は先頭から全部、"+10" は最後の 1 バイト、"+11" は何も出さない
+        for (num, expected) in [("+1", text), ("+0", text), ("+10", "9"), ("+11", "")] {
+            let mut out = Vec::new();
+            print_byte(Cursor::new(text), &parse_byte_num(num).unwrap(), 10, &mut out).unwrap();
+            assert_eq!(String::from_utf8_lossy(&out), expected);
+        }
+    }
+
+    #[test]
+    fn test_print_last_lines() {
+        let text = "one\ntwo\nthree\nfour";
+        let mut out = Vec::new();
+        print_last_lines(Cursor::new(text), 2, &mut out).unwrap();
+        assert_eq!(String::from_utf8_lossy(&out), "three\nfour");
+
+        let mut out = Vec::new();
+        print_last_lines(Cursor::new(text), 20, &mut out).unwrap();
+        assert_eq!(String::from_utf8_lossy(&out), text);
+    }
+
+    #[test]
+    fn test_print_last_lines_reads_once() {
+        let text = fs::read("tests/inputs/ten.txt").unwrap();
+        let mut reader = CountingReader {
+            inner: Cursor::new(text.clone()),
+            bytes_read: 0,
+        };
+        let mut out = Vec::new();
+        print_last_lines(BufReader::new(&mut reader), 3, &mut out).unwrap();
+        assert_eq!(reader.bytes_read, text.len());
+        assert_eq!(out.iter().filter(|&&b| b == b'\n').count(), 3);
+    }
+
+    #[test]
+    fn test_follow_poll() {
+        let path = temp_file("follow", "first\n");
+        let mut follower = Follower::new(false, 0);
+        add_at_end(&mut follower, &path);
+
+        // 変化がなければ何も出力しない
+        let mut out = Vec::new();
+        follower.poll(&mut out).unwrap();
+        assert!(out.is_empty());
+
+        // 追記された分だけを出力する
+        append(&path, "second\nthird\n");
+        follower.poll(&mut out).unwrap();
+        assert_eq!(String::from_utf8_lossy(&out), "second\nthird\n");
+
+        // 切り詰められた場合は先頭から出力し直す
+        fs::write(&path, "new\n").unwrap();
+        let mut out = Vec::new();
+        follower.poll(&mut out).unwrap();
+        assert_eq!(String::from_utf8_lossy(&out), "new\n");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_follow_from_print_tail() {
+        let path = temp_file("follow-print-tail", "first\nsecond\n");
+        let mut reader = BufReader::new(File::open(&path).unwrap());
+        let mut out = Vec::new();
+        let pos = print_tail(&mut reader, &TailMode::Lines(TakeNum(-1)), &mut out).unwrap();
+        assert_eq!(String::from_utf8_lossy(&out), "second\n");
+        assert_eq!(pos, 13);
+
+        // print_tail の後、follow を始めるまでの間に追記された分も出力する
+        append(&path, "third\n");
+        let mut follower = Follower::new(false, 0);
+        follower.add(path.to_str().unwrap(), reader.into_inner(), pos).unwrap();
+        let mut out = Vec::new();
+        follower.poll(&mut out).unwrap();
+        assert_eq!(String::from_utf8_lossy(&out), "third\n");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_print_tail_pos_without_output() {
+        // 何も出力しなくても、数えたところまでを読んだ位置として返す
+        let text = "first\nsecond\n";
+        let modes = [
+            TailMode::Lines(TakeNum(0)),
+            TailMode::Lines(TakeNum(5)),
+            TailMode::Bytes(TakeNum(0)),
+            TailMode::Bytes(TakeNum(100)),
+        ];
+        for mode in &modes {
+            let mut out = Vec::new();
+            let pos = print_tail(Cursor::new(text), mode, &mut out).unwrap();
+            assert!(out.is_empty());
+            assert_eq!(pos, text.len() as u64);
+        }
+    }
+
+    #[test]
+    fn test_follow_poll_headers() {
+        let path1 = temp_file("follow-headers-1", "");
+        let path2 = temp_file("follow-headers-2", "");
+        let name1 = path1.to_str().unwrap();
+        let name2 = path2.to_str().unwrap();
+        let mut follower = Follower::new(true, 0);
+        add_at_end(&mut follower, &path1);
+        add_at_end(&mut follower, &path2);
+
+        // 最後に出力したファイルと同じならヘッダーは出さない
+        append(&path2, "b1\n");
+        let mut out = Vec::new();
+        follower.poll(&mut out).unwrap();
+        assert_eq!(String::from_utf8_lossy(&out), "b1\n");
+
+        append(&path1, "a1\n");
+        append(&path2, "b2\n");
+        let mut out = Vec::new();
+        follower.poll(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&out),
+            format!("\n==> {} <==\na1\n\n==> {} <==\nb2\n", name1, name2)
+        );
+
+        fs::remove_file(&path1).unwrap();
+        fs::remove_file(&path2).unwrap();
+    }
+
+    #[test]
+    fn test_follow_poll_rotated() {
+        let path = temp_file("follow-rotate", "first\n");
+        let rotated = path.with_extension("1");
+        let mut follower = Follower::new(false, 0);
+        add_at_end(&mut follower, &path);
+
+        // rename されただけなら元のファイルを追い続ける
+        fs::rename(&path, &rotated).unwrap();
+        append(&rotated, "second\n");
+        let mut out = Vec::new();
+        follower.poll(&mut out).unwrap();
+        assert_eq!(String::from_utf8_lossy(&out), "second\n");
+
+        // 同じ名前で作り直されたら、元のファイルの残りを出してから新しいファイルを先頭から出す
+        append(&rotated, "third\n");
+        fs::write(&path, "new1\nnew2\n").unwrap();
+        let mut out = Vec::new();
+        follower.poll(&mut out).unwrap();
+        assert_eq!(String::from_utf8_lossy(&out), "third\nnew1\nnew2\n");
+
+        // 以降は新しいファイルへの追記を出す
+        append(&rotated, "lost\n");
+        append(&path, "new3\n");
+        let mut out = Vec::new();
+        follower.poll(&mut out).unwrap();
+        assert_eq!(String::from_utf8_lossy(&out), "new3\n");
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&rotated).unwrap();
+    }
+
+    #[test]
+    fn test_follow_poll_max_unchanged_stats() {
+        let path = temp_file("follow-unchanged", "first\n");
+        let rotated = path.with_extension("1");
+        let mut follower = Follower::new(false, 2);
+        add_at_end(&mut follower, &path);
+        fs::rename(&path, &rotated).unwrap();
+        fs::write(&path, "new\n").unwrap();
+
+        // 大きさが変わらないのが 2 回続くまでは名前で stat し直さない
+        let mut out = Vec::new();
+        follower.poll(&mut out).unwrap();
+        assert!(out.is_empty());
+
+        follower.poll(&mut out).unwrap();
+        assert_eq!(String::from_utf8_lossy(&out), "new\n");
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&rotated).unwrap();
+    }
+
+    // 終了して回収済みの子プロセスの pid
+    fn dead_pid() -> i32 {
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        child.wait().unwrap();
+        child.id() as i32
+    }
+
+    #[test]
+    fn test_process_alive() {
+        assert!(process_alive(std::process::id() as i32));
+        assert!(!process_alive(dead_pid()));
+    }
+
+    #[test]
+    fn test_follow_exits_when_pid_dead() {
+        let path = temp_file("follow-pid", "first\n");
+        let mut follower = Follower::new(false, 0);
+        add_at_end(&mut follower, &path);
+        append(&path, "second\n");
+
+        // プロセスが終了していれば、最後に一度 poll してから抜ける
+        let mut out = Vec::new();
+        follow(&mut follower, Some(dead_pid()), &mut out).unwrap();
+        assert_eq!(String::from_utf8_lossy(&out), "second\n");
+
+        fs::remove_file(&path).unwrap();
+    }
 }
@@ -34,11 +34,15 @@ fn gen_bad_file() -> String {
 
 // --------------------------------------------------
 #[test]
-fn dies_no_args() -> TestResult {
+fn no_args_reads_stdin() -> TestResult {
+    let input = fs::read_to_string(TEN)?;
+    let expected = fs::read_to_string("tests/expected/ten.txt.n3.out")?;
     Command::cargo_bin(PRG)?
+        .args(["-n", "3"])
+        .write_stdin(input)
         .assert()
-        .failure()
-        .stderr(predicate::str::contains("Usage"));
+        .success()
+        .stdout(expected);
 
     Ok(())
 }
@@ -47,9 +51,9 @@ fn dies_no_args() -> TestResult {
 #[test]
 fn dies_bad_bytes() -> TestResult {
     let bad = random_string();
-    let expected = format!("illegal byte count -- {}", &bad);
+    let expected = format!("illegal byte count -- {} (-c/--bytes)", &bad);
     Command::cargo_bin(PRG)?
-        .args(&["-c", &bad, EMPTY])
+        .args(["-c", &bad, EMPTY])
         .assert()
         .failure()
         .stderr(predicate::str::contains(expected));
@@ -61,9 +65,9 @@ fn dies_bad_bytes() -> TestResult {
 #[test]
 fn dies_bad_lines() -> TestResult {
     let bad = random_string();
-    let expected = format!("illegal line count -- {}", &bad);
+    let expected = format!("illegal line count -- {} (-n/--lines)", &bad);
     Command::cargo_bin(PRG)?
-        .args(&["-n", &bad, EMPTY])
+        .args(["-n", &bad, EMPTY])
         .assert()
         .failure()
         .stderr(predicate::str::contains(expected));
@@ -78,7 +82,7 @@ fn dies_bytes_and_lines() -> TestResult {
                used with '--bytes <BYTES>'";
 
     Command::cargo_bin(PRG)?
-        .args(&["-n", "1", "-c", "2"])
+        .args(["-n", "1", "-c", "2"])
         .assert()
         .failure()
         .stderr(predicate::str::contains(msg));
@@ -92,7 +96,7 @@ fn skips_bad_file() -> TestResult {
     let bad = gen_bad_file();
     let expected = format!("{}: .* [(]os error 2[)]", bad);
     Command::cargo_bin(PRG)?
-        .args(&[ONE, &bad, TWO])
+        .args([ONE, &bad, TWO])
         .assert()
         .stderr(predicate::str::is_match(expected)?);
 
@@ -705,6 +709,17 @@ fn ten_n_plus_2() -> TestResult {
     run(&[TEN, "-n", "+2"], "tests/expected/ten.txt.n+2.out")
 }
 
+#[test]
+fn ten_n_plus_3() -> TestResult {
+    // 末尾の3行ではなく、3行目から最後まで
+    run(&[TEN, "-n", "+3"], "tests/expected/ten.txt.n+3.out")
+}
+
+#[test]
+fn ten_lines_plus_3() -> TestResult {
+    run(&[TEN, "--lines=+3"], "tests/expected/ten.txt.n+3.out")
+}
+
 #[test]
 fn ten_c_plus_0() -> TestResult {
     run(&[TEN, "-c", "+0"], "tests/expected/ten.txt.c+0.out")
@@ -829,3 +844,187 @@ fn multiple_files_c_plus_3() -> TestResult {
         "tests/expected/all.c+3.out",
     )
 }
+
+// --------------------------------------------------
+fn run_stdin(args: &[&str], input_file: &str, expected_file: &str) -> TestResult {
+    let input = fs::read(input_file)?;
+    let mut file = File::open(expected_file)?;
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer)?;
+    let expected = String::from_utf8_lossy(&buffer);
+
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(predicate::eq(expected.as_bytes() as &[u8]));
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn ten_stdin_n3() -> TestResult {
+    run_stdin(&["-n", "3", "-"], TEN, "tests/expected/ten.txt.n3.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn ten_stdin_n_plus2() -> TestResult {
+    run_stdin(&["-n", "+2", "-"], TEN, "tests/expected/ten.txt.n+2.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn ten_stdin_c3() -> TestResult {
+    run_stdin(&["-c", "3", "-"], TEN, "tests/expected/ten.txt.c3.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn stdin_between_files() -> TestResult {
+    run_stdin(&["-n", "1", ONE, "-", TWO], TEN, "tests/expected/one_stdin_two.n1.out")
+}
+
+// --------------------------------------------------
+// stdout と stderr をまとめて受け取り、ヘッダーと中身がファイルごとに順番通りに出ることを確かめる
+#[test]
+#[cfg(unix)]
+fn headers_before_content_combined_output() -> TestResult {
+    let bad = gen_bad_file();
+    let bin = assert_cmd::cargo::cargo_bin(PRG);
+    let script = format!("{} -n 1 {} {} {} 2>&1", bin.display(), ONE, bad, TWO);
+    let output = std::process::Command::new("sh").args(["-c", &script]).output()?;
+    let combined = String::from_utf8(output.stdout)?;
+
+    let expected = format!(
+        "==> {} <==\nÖne line, four wordś.\n{}: .* [(]os error 2[)]\n\n==> {} <==\nFour words.\n",
+        ONE, bad, TWO
+    );
+    assert!(
+        predicate::str::is_match(format!("^{}$", expected))?.eval(&combined),
+        "unexpected output: {:?}",
+        combined
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_pid_without_follow() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([TWO, "--pid", "1"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--follow"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_pid_not_positive() -> TestResult {
+    for pid in ["0", "-1"] {
+        Command::cargo_bin(PRG)?
+            .args([TWO, "-f", &format!("--pid={}", pid)])
+            .timeout(std::time::Duration::from_secs(5))
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(format!("invalid PID -- {} (--pid)", pid)));
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_stops_when_pid_dead() -> TestResult {
+    let mut child = std::process::Command::new("true").spawn()?;
+    child.wait()?;
+    let expected = fs::read_to_string("tests/expected/two.txt.out")?;
+    Command::cargo_bin(PRG)?
+        .args([TWO, "-f", "--pid", &child.id().to_string()])
+        .timeout(std::time::Duration::from_secs(5))
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_max_unchanged_stats_without_follow() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([TWO, "--max-unchanged-stats", "1"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--follow"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn stdin_c_plus_5() -> TestResult {
+    // 10 バイトの入力の 5 バイト目から 10 バイト目まで
+    Command::cargo_bin(PRG)?
+        .args(["-c", "+5"])
+        .write_stdin("0123456789")
+        .assert()
+        .success()
+        .stdout("456789");
+    Ok(())
+}
+
+#[test]
+fn stdin_c_minus_5() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-c=-5"])
+        .write_stdin("0123456789")
+        .assert()
+        .success()
+        .stdout("56789");
+    Ok(())
+}
+
+#[test]
+fn stdin_bytes_from_start_5() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--bytes-from-start", "5"])
+        .write_stdin("0123456789")
+        .assert()
+        .success()
+        .stdout("456789");
+    Ok(())
+}
+
+#[test]
+fn ten_bytes_from_start_matches_c_plus() -> TestResult {
+    let expected = Command::cargo_bin(PRG)?.args([TEN, "-c", "+8"]).output()?.stdout;
+    Command::cargo_bin(PRG)?
+        .args([TEN, "--bytes-from-start", "8"])
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+#[test]
+fn dies_bytes_from_start_signed() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([TEN, "--bytes-from-start=-5"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "illegal byte count -- -5 (--bytes-from-start)",
+        ));
+    Ok(())
+}
+
+#[test]
+fn dies_bytes_from_start_and_bytes() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([TEN, "--bytes-from-start", "5", "-c", "3"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
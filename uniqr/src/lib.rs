@@ -1,5 +1,5 @@
-use clap::Parser;
-use std::{error::Error, fs::File, io::{self, BufRead, BufReader, BufWriter, Write}};
+use clap::{Args, Parser};
+use std::{borrow::Cow, error::Error, fs::File, io::{self, BufRead, BufReader, BufWriter, Write}};
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
@@ -17,6 +17,56 @@ pub struct Config {
     /// Show counts
     #[arg(short, long)]
     count: bool,
+
+    /// Only print duplicate lines
+    #[arg(short = 'd', long = "repeated", conflicts_with = "only_unique")]
+    only_repeated: bool,
+
+    /// Only print unique lines
+    #[arg(short = 'u', long = "unique")]
+    only_unique: bool,
+
+    /// Write lines that were not repeated to FILE instead of OUT_FILE
+    #[arg(long = "uniq-to", value_name = "FILE")]
+    uniq_to: Option<String>,
+
+    /// Write one copy of each repeated line to FILE instead of OUT_FILE
+    #[arg(long = "dup-to", value_name = "FILE")]
+    dup_to: Option<String>,
+
+    #[command(flatten)]
+    opts: UniqOpts,
+}
+
+// 行の比較方法に関するオプション
+#[derive(Debug, Default, Args)]
+pub struct UniqOpts {
+    /// Case-insensitive comparison of lines
+    #[arg(short = 'i', long)]
+    ignore_case: bool,
+
+    /// Avoid comparing the first N fields
+    #[arg(short = 'f', long, value_name = "N", default_value = "0")]
+    skip_fields: usize,
+
+    /// Avoid comparing the first N characters
+    #[arg(short = 's', long, value_name = "N", default_value = "0")]
+    skip_chars: usize,
+
+    /// Compare no more than N characters
+    #[arg(short = 'w', long, value_name = "N")]
+    check_chars: Option<usize>,
+
+    /// Line delimiter is NUL, not newline
+    #[arg(short = 'z', long)]
+    zero_terminated: bool,
+}
+
+impl UniqOpts {
+    // 1レコードの終わりを表すバイト
+    fn terminator(&self) -> u8 {
+        if self.zero_terminated { b'\0' } else { b'\n' }
+    }
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -37,56 +87,315 @@ fn open_out(filename: &Option<String>) -> MyResult<Box<dyn Write>> {
     }
 }
 
-fn print_line(count_flg: bool, count: usize, line: &String, file_out: &mut Box<dyn Write>) -> MyResult<()> {
-    if count_flg {
-        write!(file_out, "{:>4} {}", count, line)?;
-    } else {
-        write!(file_out, "{}", line)?;
-    }   
+// 出力先
+// uniq / dup が指定されていれば、1回だけ現れた行と繰り返し現れた行をそれぞれそちらに振り分ける
+// 指定されていない方は main に書き出す
+struct Outputs<'a> {
+    main: &'a mut dyn Write,
+    uniq: Option<&'a mut dyn Write>,
+    dup: Option<&'a mut dyn Write>,
+}
+
+impl Outputs<'_> {
+    // count 回現れた行の出力先を返す
+    fn route(&mut self, count: usize) -> &mut dyn Write {
+        let routed = if count > 1 { &mut self.dup } else { &mut self.uniq };
+        match routed {
+            Some(w) => &mut **w,
+            None => &mut *self.main,
+        }
+    }
+}
+
+// line は読んだときのバイト列そのままで、終端 (改行、CRLF、NUL。最後の行ではないこともある) も含む
+fn print_line(config: &Config, count: usize, line: &[u8], file_out: &mut dyn Write) -> MyResult<()> {
+    // -d なら2回以上、-u なら1回だけ現れた行のみ出力する
+    if (config.only_repeated && count < 2) || (config.only_unique && count != 1) {
+        return Ok(());
+    }
+
+    if config.count {
+        write!(file_out, "{:>4} ", count)?;
+    }
+    file_out.write_all(line)?;
 
     Ok(())
 }
 
+// 比較に使う部分を返す
+// 先頭の空白とそれに続く非空白の並びを1フィールドとして skip_fields 個読み飛ばし、
+// その後さらに skip_chars 文字読み飛ばす
+fn comparison_key(line: &str, skip_fields: usize, skip_chars: usize) -> &str {
+    let mut rest = line;
+    for _ in 0..skip_fields {
+        rest = rest.trim_start_matches(char::is_whitespace);
+        rest = rest.trim_start_matches(|c: char| !c.is_whitespace());
+    }
+
+    match rest.char_indices().nth(skip_chars) {
+        Some((i, _)) => &rest[i..],
+        None => "",
+    }
+}
+
+// レコードの終端の長さを返す
+// 改行区切りのときは CRLF もまとめて終端とみなす。最後のレコードでは終端がなく 0 のこともある
+fn terminator_len(record: &[u8], terminator: u8) -> usize {
+    match record {
+        [.., b'\r', b'\n'] if terminator == b'\n' => 2,
+        [.., last] if *last == terminator => 1,
+        _ => 0,
+    }
+}
+
+// 比較に使うキーを返す
+// フィールドの読み飛ばし、文字の読み飛ばし、-w による文字数の制限、大文字小文字の同一視の順に適用する
+// 出力には元の行をそのまま使うので、小文字にするのは比較用のコピーだけ
+fn key<'a>(line: &'a str, opts: &UniqOpts) -> Cow<'a, str> {
+    let record = &line[..line.len() - terminator_len(line.as_bytes(), opts.terminator())];
+    let key = comparison_key(record, opts.skip_fields, opts.skip_chars);
+    let key = match opts.check_chars.and_then(|n| key.char_indices().nth(n)) {
+        Some((i, _)) => &key[..i],
+        None => key,
+    };
+
+    if opts.ignore_case {
+        Cow::Owned(key.to_lowercase())
+    } else {
+        Cow::Borrowed(key)
+    }
+}
+
 pub fn run(config: Config) -> MyResult<()> {
-    let mut file_in = open_in(&config.in_file)
+    let file_in = open_in(&config.in_file)
         .map_err(|e| format!("{}: {}", config.in_file, e))?;
 
     let mut file_out = open_out(&config.out_file)
-        .map_err(|e| format!("{}: {}", config.out_file.unwrap_or("stdout".to_string()), e))?;
+        .map_err(|e| format!("{}: {}", config.out_file.as_deref().unwrap_or("stdout"), e))?;
+
+    let mut uniq_out = match &config.uniq_to {
+        Some(path) => Some(open_out(&config.uniq_to).map_err(|e| format!("{}: {}", path, e))?),
+        None => None,
+    };
+    let mut dup_out = match &config.dup_to {
+        Some(path) => Some(open_out(&config.dup_to).map_err(|e| format!("{}: {}", path, e))?),
+        None => None,
+    };
+
+    let mut outputs = Outputs {
+        main: &mut file_out,
+        uniq: uniq_out.as_mut().map(|w| w.as_mut() as &mut dyn Write),
+        dup: dup_out.as_mut().map(|w| w.as_mut() as &mut dyn Write),
+    };
+    dedup(&config, file_in, &mut outputs)
+}
 
+// 隣り合う同じレコードをまとめて、現れた回数に応じた出力先に書き出す
+// レコードは -z なら NUL、そうでなければ改行で区切る
+// 比較は終端を除いて行うが、出力には各グループの最初のレコードを読んだバイト列のまま使う
+// そのため CRLF は CRLF のまま出て、終端のない最後の行はそれがグループの先頭なら終端なしで出る
+// (最後の行が前の行と同じなら、前の行の終端付きで出る)
+// 比較用のキーだけは UTF-8 として読むので、不正なバイト列も出力はそのまま残る
+fn dedup(config: &Config, mut file_in: impl BufRead, outputs: &mut Outputs) -> MyResult<()> {
+    let terminator = config.opts.terminator();
     let mut count: usize = 0;
-    let mut prev_line = String::new();
+    let mut prev_line: Vec<u8> = Vec::new();
+    // prev_line の比較用のキー。行ごとに前の行をデコードし直さないように、入れ替えるときに覚えておく
+    let mut prev_key = String::new();
 
     loop {
-        let mut line = String::new();
-        let bytes = file_in.read_line(&mut line)?;
+        let mut line = Vec::new();
+        let bytes = file_in.read_until(terminator, &mut line)?;
         if bytes == 0 {
-            if count > 0 { print_line(config.count, count, &prev_line, &mut file_out)? }
+            if count > 0 { print_line(config, count, &prev_line, outputs.route(count))? }
             break;
         }
 
-        match (prev_line.trim_matches('\n') == line.trim_matches('\n'), count) {
-            (_, 0) => {
-                count += 1;
-                prev_line = line;
-            }
-            (true, _) => {
-                // 最終行とその前の行の違いが改行の有無しかない場合、それは同じものとして処理するので
-                // その場合にここで prev_line = line をしてしまうと
-                // prev_line を改行がないもので上書きしてしまう
-                count += 1;
-            }
-            (false, _) => {
-                print_line(config.count, count, &prev_line, &mut file_out)?;
-                count = 1;
-                prev_line = line;
+        let decoded = String::from_utf8_lossy(&line);
+        let line_key = key(&decoded, &config.opts);
+        if count > 0 && line_key == prev_key.as_str() {
+            count += 1;
+        } else {
+            if count > 0 {
+                print_line(config, count, &prev_line, outputs.route(count))?;
             }
+            count = 1;
+            prev_key = line_key.into_owned();
+            prev_line = line;
         }
     }
 
     Ok(())
 }
 
+#[cfg(test)]
+mod tests {
+    use super::{comparison_key, dedup, key, terminator_len, Config, Outputs, UniqOpts};
+    use clap::Parser;
+    use std::io::Cursor;
+
+    fn run_dedup(args: &[&str], input: &[u8]) -> Vec<u8> {
+        let config = Config::try_parse_from(["uniqr"].iter().chain(args)).unwrap();
+        let mut out = Vec::new();
+        let mut outputs = Outputs { main: &mut out, uniq: None, dup: None };
+        dedup(&config, Cursor::new(input), &mut outputs).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_comparison_key() {
+        // 何も読み飛ばさない
+        assert_eq!(comparison_key("a b c", 0, 0), "a b c");
+
+        // フィールドのみ
+        assert_eq!(comparison_key("10:00 foo bar", 1, 0), " foo bar");
+        assert_eq!(comparison_key("  10:00\tfoo bar", 2, 0), " bar");
+
+        // 文字のみ
+        assert_eq!(comparison_key("abcdef", 2, 0), "");
+        assert_eq!(comparison_key("abcdef", 0, 2), "cdef");
+        assert_eq!(comparison_key("あいう", 0, 1), "いう");
+
+        // フィールドを読み飛ばしてから文字を読み飛ばす
+        assert_eq!(comparison_key("10:00 foo bar", 1, 1), "foo bar");
+        assert_eq!(comparison_key("x abc", 1, 2), "bc");
+
+        // 読み飛ばしすぎると空になる
+        assert_eq!(comparison_key("a b", 5, 0), "");
+        assert_eq!(comparison_key("abc", 0, 10), "");
+        assert_eq!(comparison_key("a b", 1, 5), "");
+    }
+
+    #[test]
+    fn test_key() {
+        let line = "10:00  Foo Bar Baz\n";
+
+        // 何も指定しなければ改行を除いた行そのもの
+        let opts = UniqOpts::default();
+        assert_eq!(key(line, &opts), "10:00  Foo Bar Baz");
+
+        // 大文字小文字の同一視のみ
+        let opts = UniqOpts { ignore_case: true, ..Default::default() };
+        assert_eq!(key(line, &opts), "10:00  foo bar baz");
+
+        // フィールドの読み飛ばしのみ
+        let opts = UniqOpts { skip_fields: 2, ..Default::default() };
+        assert_eq!(key(line, &opts), " Bar Baz");
+
+        // 文字の読み飛ばしのみ
+        let opts = UniqOpts { skip_chars: 7, ..Default::default() };
+        assert_eq!(key(line, &opts), "Foo Bar Baz");
 
+        // 文字数の制限のみ
+        let opts = UniqOpts { check_chars: Some(5), ..Default::default() };
+        assert_eq!(key(line, &opts), "10:00");
+        let opts = UniqOpts { check_chars: Some(100), ..Default::default() };
+        assert_eq!(key(line, &opts), "10:00  Foo Bar Baz");
+        let opts = UniqOpts { check_chars: Some(0), ..Default::default() };
+        assert_eq!(key(line, &opts), "");
+
+        // すべて組み合わせる
+        // フィールド、文字、文字数の制限、大文字小文字の順に適用される
+        let opts = UniqOpts {
+            ignore_case: true,
+            skip_fields: 1,
+            skip_chars: 2,
+            check_chars: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(key(line, &opts), "foo");
+
+        // 読み飛ばしすぎると空になる
+        let opts = UniqOpts { skip_fields: 10, check_chars: Some(3), ..Default::default() };
+        assert_eq!(key(line, &opts), "");
+    }
+
+    #[test]
+    fn test_dedup() {
+        assert_eq!(run_dedup(&[], b"a\na\nb\na"), b"a\nb\na");
+        assert_eq!(run_dedup(&["-c"], b"a\na\nb\n"), b"   2 a\n   1 b\n");
+    }
 
+    #[test]
+    fn test_dedup_zero_terminated() {
+        // NUL で区切ったレコードごとに比較する。改行はレコードの一部として扱う
+        let input = b"a\0a\0b\nc\0b\nc\0d";
+        assert_eq!(run_dedup(&["-z"], input), b"a\0b\nc\0d");
+        assert_eq!(run_dedup(&["-z", "-c"], input), b"   2 a\0   2 b\nc\0   1 d");
 
+        // 最後のレコードは NUL がなくても同じものとして扱う
+        assert_eq!(run_dedup(&["-z", "-c"], b"x\0x"), b"   2 x\0");
+
+        // -z がなければ NUL は区切りにならない
+        assert_eq!(run_dedup(&[], b"a\0a\0"), b"a\0a\0");
+    }
+
+    #[test]
+    fn test_terminator_len() {
+        assert_eq!(terminator_len(b"a\n", b'\n'), 1);
+        assert_eq!(terminator_len(b"a\r\n", b'\n'), 2);
+        assert_eq!(terminator_len(b"a", b'\n'), 0);
+        assert_eq!(terminator_len(b"a\r", b'\n'), 0);
+        assert_eq!(terminator_len(b"\r\n", b'\n'), 2);
+        assert_eq!(terminator_len(b"a\0", b'\0'), 1);
+        // NUL 区切りのときは CRLF も改行もレコードの一部
+        assert_eq!(terminator_len(b"a\r\n", b'\0'), 0);
+    }
+
+    #[test]
+    fn test_dedup_crlf() {
+        // CRLF は出力でもそのまま残る
+        assert_eq!(run_dedup(&[], b"a\r\na\r\nb\r\n"), b"a\r\nb\r\n");
+        assert_eq!(run_dedup(&["-c"], b"a\r\na\r\nb\r\n"), b"   2 a\r\n   1 b\r\n");
+
+        // 終端の違い (CRLF、LF、終端なし) は比較に影響せず、グループの最初の行の終端で出る
+        assert_eq!(run_dedup(&["-c"], b"a\r\na\na"), b"   3 a\r\n");
+        assert_eq!(run_dedup(&[], b"a\na\r\nb\r\nb"), b"a\nb\r\n");
+
+        // 行の途中の '\r' は終端ではない
+        assert_eq!(run_dedup(&[], b"a\rb\nab\n"), b"a\rb\nab\n");
+    }
+
+    #[test]
+    fn test_dedup_no_final_newline() {
+        // 最後の行に終端がなければ、出力の最後にも付けない
+        assert_eq!(run_dedup(&[], b"a\nb"), b"a\nb");
+        assert_eq!(run_dedup(&[], b"a\r\nb"), b"a\r\nb");
+        assert_eq!(run_dedup(&["-c"], b"a\nb\nb"), b"   1 a\n   2 b\n");
+        assert_eq!(run_dedup(&["-z"], b"a\0b"), b"a\0b");
+
+        // 最後の行がグループの先頭でなければ、先頭の行の終端で出る
+        assert_eq!(run_dedup(&[], b"a\na"), b"a\n");
+
+        // 空の入力と、改行だけの入力
+        assert_eq!(run_dedup(&[], b""), b"");
+        assert_eq!(run_dedup(&["-c"], b"\n\n"), b"   2 \n");
+    }
+
+    #[test]
+    fn test_dedup_invalid_utf8() {
+        // UTF-8 として読めないバイト列も、そのまま出力する
+        assert_eq!(run_dedup(&[], b"\xff\n\xff\nb\n"), b"\xff\nb\n");
+    }
+
+    #[test]
+    fn test_dedup_split() {
+        let config = Config::try_parse_from(["uniqr", "-c"]).unwrap();
+        let input = b"a\na\nb\nc\nc\nc\nd\n";
+
+        // 1回だけ現れた行と繰り返し現れた行を別々の出力に振り分ける
+        let (mut main, mut uniq, mut dup) = (Vec::new(), Vec::new(), Vec::new());
+        let mut outputs = Outputs { main: &mut main, uniq: Some(&mut uniq), dup: Some(&mut dup) };
+        dedup(&config, Cursor::new(input), &mut outputs).unwrap();
+        assert_eq!(main, b"");
+        assert_eq!(uniq, b"   1 b\n   1 d\n");
+        assert_eq!(dup, b"   2 a\n   3 c\n");
+
+        // 片方だけなら、もう片方は main に書き出す
+        let (mut main, mut dup) = (Vec::new(), Vec::new());
+        let mut outputs = Outputs { main: &mut main, uniq: None, dup: Some(&mut dup) };
+        dedup(&config, Cursor::new(input), &mut outputs).unwrap();
+        assert_eq!(main, b"   1 b\n   1 d\n");
+        assert_eq!(dup, b"   2 a\n   3 c\n");
+    }
+}
@@ -86,6 +86,11 @@ const SPACE: Test = Test {
     out_count: "tests/expected/only_space.c.out",
 };
 
+const DUP: &str = "tests/inputs/dup.txt";
+const CASE: &str = "tests/inputs/case.txt";
+const LOG: &str = "tests/inputs/log.txt";
+const WIDTH: &str = "tests/inputs/width.txt";
+
 // --------------------------------------------------
 fn gen_bad_file() -> String {
     loop {
@@ -130,7 +135,7 @@ fn run(test: &Test) -> TestResult {
 fn run_count(test: &Test) -> TestResult {
     let expected = fs::read_to_string(test.out_count)?;
     Command::cargo_bin(PRG)?
-        .args(&[test.input, "-c"])
+        .args([test.input, "-c"])
         .assert()
         .success()
         .stdout(expected);
@@ -168,12 +173,12 @@ fn run_outfile(test: &Test) -> TestResult {
     let outfile = NamedTempFile::new()?;
     let outpath = &outfile.path().to_str().unwrap();
     Command::cargo_bin(PRG)?
-        .args(&[test.input, outpath])
+        .args([test.input, outpath])
         .assert()
         .success()
         .stdout("");
 
-    let contents = fs::read_to_string(&outpath)?;
+    let contents = fs::read_to_string(outpath)?;
     assert_eq!(&expected, &contents);
 
     Ok(())
@@ -185,13 +190,13 @@ fn run_outfile_count(test: &Test) -> TestResult {
     let outpath = &outfile.path().to_str().unwrap();
 
     Command::cargo_bin(PRG)?
-        .args(&[test.input, outpath, "--count"])
+        .args([test.input, outpath, "--count"])
         .assert()
         .success()
         .stdout("");
 
     let expected = fs::read_to_string(test.out_count)?;
-    let contents = fs::read_to_string(&outpath)?;
+    let contents = fs::read_to_string(outpath)?;
     assert_eq!(&expected, &contents);
 
     Ok(())
@@ -204,13 +209,13 @@ fn run_stdin_outfile_count(test: &Test) -> TestResult {
     let outpath = &outfile.path().to_str().unwrap();
 
     Command::cargo_bin(PRG)?
-        .args(&["-", outpath, "-c"])
+        .args(["-", outpath, "-c"])
         .write_stdin(input)
         .assert()
         .stdout("");
 
     let expected = fs::read_to_string(test.out_count)?;
-    let contents = fs::read_to_string(&outpath)?;
+    let contents = fs::read_to_string(outpath)?;
     assert_eq!(&expected, &contents);
 
     Ok(())
@@ -616,4 +621,114 @@ fn t6_stdin_outfile_count() -> TestResult {
 #[test]
 fn only_space_count() -> TestResult {
     run_count(&SPACE)
-}
\ No newline at end of file
+}
+// --------------------------------------------------
+fn run_args(args: &[&str], expected_file: &str) -> TestResult {
+    let expected = fs::read_to_string(expected_file)?;
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+#[test]
+fn dup_repeated() -> TestResult {
+    run_args(&[DUP, "-d"], "tests/expected/dup.txt.d.out")
+}
+
+#[test]
+fn dup_repeated_count() -> TestResult {
+    run_args(&[DUP, "-d", "-c"], "tests/expected/dup.txt.d.c.out")
+}
+
+#[test]
+fn dup_unique() -> TestResult {
+    run_args(&[DUP, "-u"], "tests/expected/dup.txt.u.out")
+}
+
+#[test]
+fn dup_unique_count() -> TestResult {
+    run_args(&[DUP, "--unique", "--count"], "tests/expected/dup.txt.u.c.out")
+}
+
+#[test]
+fn dies_repeated_and_unique() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([DUP, "-d", "-u"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+#[test]
+fn case_sensitive() -> TestResult {
+    run_args(&[CASE], "tests/expected/case.txt.out")
+}
+
+#[test]
+fn case_ignore_case() -> TestResult {
+    run_args(&[CASE, "-i"], "tests/expected/case.txt.i.out")
+}
+
+#[test]
+fn case_ignore_case_count() -> TestResult {
+    run_args(&[CASE, "--ignore-case", "-c"], "tests/expected/case.txt.i.c.out")
+}
+
+#[test]
+fn log_skip_fields_count() -> TestResult {
+    run_args(&[LOG, "-f", "1", "-c"], "tests/expected/log.txt.f1.c.out")
+}
+
+#[test]
+fn log_skip_chars() -> TestResult {
+    run_args(&[LOG, "--skip-chars", "5"], "tests/expected/log.txt.s5.out")
+}
+
+#[test]
+fn width_check_chars() -> TestResult {
+    run_args(&[WIDTH, "-w", "3"], "tests/expected/width.txt.w3.out")
+}
+
+#[test]
+fn width_check_chars_ignore_case_count() -> TestResult {
+    run_args(&[WIDTH, "--check-chars", "3", "-i", "-c"], "tests/expected/width.txt.w3.i.c.out")
+}
+
+#[test]
+fn dup_split_uniq_to_dup_to() -> TestResult {
+    let uniq_file = NamedTempFile::new()?;
+    let dup_file = NamedTempFile::new()?;
+    let uniq_path = uniq_file.path().to_str().unwrap();
+    let dup_path = dup_file.path().to_str().unwrap();
+
+    Command::cargo_bin(PRG)?
+        .args(["--uniq-to", uniq_path, "--dup-to", dup_path])
+        .write_stdin("a\na\nb\nc\nc\nd\n")
+        .assert()
+        .success()
+        .stdout("");
+
+    assert_eq!(fs::read_to_string(uniq_path)?, "b\nd\n");
+    assert_eq!(fs::read_to_string(dup_path)?, "a\nc\n");
+    Ok(())
+}
+
+#[test]
+fn dup_to_only() -> TestResult {
+    let dup_file = NamedTempFile::new()?;
+    let dup_path = dup_file.path().to_str().unwrap();
+
+    Command::cargo_bin(PRG)?
+        .args(["--dup-to", dup_path])
+        .write_stdin("a\na\nb\n")
+        .assert()
+        .success()
+        .stdout("b\n");
+
+    assert_eq!(fs::read_to_string(dup_path)?, "a\n");
+    Ok(())
+}
@@ -1,13 +1,16 @@
-use std::{error::Error, fs::File, io::{self, BufRead, BufReader}, ops::{Add, AddAssign}};
+use std::{error::Error, fmt, fs::{self, File}, io::{self, BufRead, BufReader}, ops::{Add, AddAssign}};
 
 use clap::Parser;
+use serde_json::{json, Value};
+use unicode_width::UnicodeWidthChar;
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
 #[derive(Debug, Parser)]
 #[command(version, about)]
 struct Args {
-    /// Input file(s) [default is stdin if not specified]
+    /// Input file(s) [default is stdin if not specified; "-" reads stdin]
+    // "-" を複数回指定するとそれぞれ stdin を読むが、最初の "-" で読み切るので 2 回目以降は 0 になる
     #[arg(value_name = "FILE")]
     files: Option<Vec<String>>,
 
@@ -26,69 +29,123 @@ struct Args {
     /// Show charactor count
     #[arg(short('m'), long, conflicts_with("bytes"))]
     chars: bool,
+
+    /// Show the display width of the longest line (wide characters count as 2)
+    #[arg(short('L'), long("max-line-length"))]
+    max_line: bool,
+
+    /// Only show files with at least this many lines
+    #[arg(long, value_name = "LINES")]
+    min_lines: Option<usize>,
+
+    /// Only show files with at most this many lines
+    #[arg(long, value_name = "LINES")]
+    max_lines: Option<usize>,
+
+    /// Include files hidden by --min-lines/--max-lines in the total
+    #[arg(long)]
+    total_all: bool,
+
+    /// Do not print the total row, even for multiple files
+    #[arg(long)]
+    no_total: bool,
+
+    /// Print counts as a JSON array
+    #[arg(long)]
+    json: bool,
+
+    /// Print the mean counts across the given files after the total
+    #[arg(long)]
+    average: bool,
 }
 
 impl Args {
+    #[allow(clippy::wrong_self_convention)]
     fn to_config(self) -> Config {
         let files = self.files;
-        match (self.lines, self.words, self.bytes, self.chars) {
-            (false, false, false, false) => Config {
-                files,
+        let filter = Filter {
+            min_lines: self.min_lines,
+            max_lines: self.max_lines,
+            total_all: self.total_all,
+        };
+        let selection = match (self.lines, self.words, self.bytes, self.chars, self.max_line) {
+            (false, false, false, false, false) => Selection {
                 lines: true,
                 words: true,
-                bytes_or_chars: ByteOrChar::Byte,
+                bytes: true,
+                ..Selection::default()
             },
-            // (bytes, chars) のパターンで場合わけ
-            (lines, words, true, false) => Config {
-                files,
+            (lines, words, bytes, chars, max_line_length) => Selection {
                 lines,
                 words,
-                bytes_or_chars: ByteOrChar::Byte,
-            },
-            (lines, words, false, true) => Config {
-                files,
-                lines, 
-                words,
-                bytes_or_chars: ByteOrChar::Char
+                bytes,
+                chars,
+                max_line_length,
             },
-            (lines, words, false, false) => Config {
-                files,
-                lines,
-                words,
-                bytes_or_chars: ByteOrChar::None,
-            },
-            _ => unreachable!("bytes and chars can't be set together")
+        };
+        Config {
+            files,
+            selection,
+            filter,
+            no_total: self.no_total,
+            json: self.json,
+            average: self.average,
         }
     }
 }
 
 #[derive(Debug)]
-enum ByteOrChar {
-    Byte,
-    Char,
-    None,
+pub struct Config {
+    files: Option<Vec<String>>,
+    selection: Selection,
+    filter: Filter,
+    // 複数ファイルでも total の行を出さない
+    no_total: bool,
+    json: bool,
+    average: bool,
 }
 
-// line, word, byte, char オプションについて
+/// Which counts to show. Counts are laid out in the order
+/// lines, words, chars, bytes, max line length.
+// line, word, byte, char, max-line-length オプションについて
 //  1. 何も指定されないがない場合は line, word, byte の3つを表示する
 //  2. それ以外は指定されたもののみを表示する
-//  3. ただし、 byte と char は共存できない
+//  3. ただし、 byte と char は共存できない (clap でチェックする)
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Selection {
+    pub lines: bool,
+    pub words: bool,
+    pub chars: bool,
+    pub bytes: bool,
+    pub max_line_length: bool,
+}
+
+// 行数によって表示するファイルを絞り込む
 #[derive(Debug)]
-pub struct Config {
-    files: Option<Vec<String>>,
-    lines: bool,
-    words: bool,
-    bytes_or_chars: ByteOrChar
+struct Filter {
+    min_lines: Option<usize>,
+    max_lines: Option<usize>,
+    // true なら絞り込まれて表示されなかったファイルも total に含める
+    total_all: bool,
+}
+
+impl Filter {
+    fn matches(&self, file_info: &FileInfo) -> bool {
+        self.min_lines.is_none_or(|min| file_info.num_lines >= min)
+            && self.max_lines.is_none_or(|max| file_info.num_lines <= max)
+    }
 }
 
 
 
+/// Counts gathered from a single input by [`count`].
 #[derive(Debug, PartialEq)]
 pub struct FileInfo {
-    num_lines: usize,
-    num_words: usize,
-    num_bytes: usize,
-    num_chars: usize,
+    pub num_lines: usize,
+    pub num_words: usize,
+    pub num_bytes: usize,
+    pub num_chars: usize,
+    pub max_line_length: usize,
 }
 
 impl Add for FileInfo {
@@ -100,6 +157,8 @@ impl Add for FileInfo {
             num_words: self.num_words + rhs.num_words,
             num_bytes: self.num_bytes + rhs.num_bytes,
             num_chars: self.num_chars + rhs.num_chars,
+            // 最長の行の長さは合計ではなく最大値をとる
+            max_line_length: self.max_line_length.max(rhs.max_line_length),
         }
     }
 }
@@ -110,16 +169,78 @@ impl AddAssign<&FileInfo> for FileInfo {
         self.num_words += rhs.num_words;
         self.num_bytes += rhs.num_bytes;
         self.num_chars += rhs.num_chars;
+        self.max_line_length = self.max_line_length.max(rhs.max_line_length);
+    }
+}
+
+impl fmt::Display for FileInfo {
+    /// Renders lines, words, chars and bytes, each right-aligned in 8 columns.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let selection = Selection {
+            lines: true,
+            words: true,
+            chars: true,
+            bytes: true,
+            max_line_length: false,
+        };
+        write!(f, "{}", self.format(&selection))
     }
 }
 
 impl FileInfo {
+    /// Renders the selected counts, each right-aligned in 8 columns.
+    pub fn format(&self, selection: &Selection) -> String {
+        [
+            (selection.lines, self.num_lines),
+            (selection.words, self.num_words),
+            (selection.chars, self.num_chars),
+            (selection.bytes, self.num_bytes),
+            (selection.max_line_length, self.max_line_length),
+        ]
+        .iter()
+        .filter(|(selected, _)| *selected)
+        .map(|(_, n)| format!("{:>8}", n))
+        .collect()
+    }
+
+    /// Renders the selected counts as a JSON object along with the file name.
+    /// Counts that were not selected are omitted.
+    pub fn to_json(&self, file: &str, selection: &Selection) -> Value {
+        let mut object = json!({ "file": file });
+        for (selected, key, n) in [
+            (selection.lines, "lines", self.num_lines),
+            (selection.words, "words", self.num_words),
+            (selection.chars, "chars", self.num_chars),
+            (selection.bytes, "bytes", self.num_bytes),
+            (selection.max_line_length, "max_line_length", self.max_line_length),
+        ] {
+            if selected {
+                object[key] = json!(n);
+            }
+        }
+        object
+    }
+
+    /// Divides each count by `n`, rounding to the nearest integer.
+    /// The max line length is kept as is.
+    pub fn average(&self, n: usize) -> FileInfo {
+        let div = |total: usize| (total + n / 2) / n;
+        FileInfo {
+            num_lines: div(self.num_lines),
+            num_words: div(self.num_words),
+            num_bytes: div(self.num_bytes),
+            num_chars: div(self.num_chars),
+            max_line_length: self.max_line_length,
+        }
+    }
+
     fn zero() -> FileInfo {
         FileInfo {
             num_lines: 0,
             num_words: 0,
             num_bytes: 0,
             num_chars: 0,
+            max_line_length: 0,
         }
     }
 }
@@ -128,8 +249,12 @@ pub fn get_config() -> MyResult<Config> {
     Ok(Args::parse().to_config())
 }
 
+// "-" は stdin として開く。呼ばれるたびに stdin の続きから読む
 fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
-    Ok(Box::new(BufReader::new(File::open(filename)?)))
+    match filename {
+        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
+        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
+    }
 }
 
 pub fn count(mut file: impl BufRead) -> MyResult<FileInfo> {
@@ -137,17 +262,33 @@ pub fn count(mut file: impl BufRead) -> MyResult<FileInfo> {
     let mut num_words = 0;
     let mut num_bytes = 0;
     let mut num_chars = 0;
-    let mut line_buf = String::new();
+    let mut max_line_length = 0;
+    // 直前のバイトが単語の途中だったか
+    let mut in_word = false;
+    let mut line_buf = Vec::new();
 
     loop {
-        let bytes = file.read_line(&mut line_buf)?;
+        line_buf.clear();
+        let bytes = file.read_until(b'\n', &mut line_buf)?;
         if bytes == 0 { break; }
 
         num_bytes += bytes;
-        num_lines += 1;
-        num_words += line_buf.split_whitespace().count();
-        num_chars += line_buf.chars().count();
-        line_buf.clear();
+        // wc と同じく改行の数を行数とする
+        if line_buf.ends_with(b"\n") {
+            num_lines += 1;
+        }
+        for &byte in &line_buf {
+            if is_word_separator(byte) {
+                in_word = false;
+            } else if !in_word {
+                in_word = true;
+                num_words += 1;
+            }
+        }
+        num_chars += count_chars(&line_buf);
+        let line = line_buf.strip_suffix(b"\n").unwrap_or(&line_buf);
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        max_line_length = max_line_length.max(display_width(line));
     }
 
 
@@ -156,24 +297,42 @@ pub fn count(mut file: impl BufRead) -> MyResult<FileInfo> {
         num_words,
         num_bytes,
         num_chars,
+        max_line_length,
     })
 }
 
+// wc が単語の区切りとして扱う空白文字 (form feed, vertical tab を含む)
+fn is_word_separator(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\n' | b'\r' | b'\x0b' | b'\x0c')
+}
+
+// UTF-8 の継続バイト以外を数えることで文字数を求める
+fn count_chars(bytes: &[u8]) -> usize {
+    bytes.iter().filter(|&&byte| byte & 0xC0 != 0x80).count()
+}
+
+// 端末に表示したときの幅を求める。全角などの幅の広い文字は 2、結合文字や制御文字は 0 と数える
+// タブは GNU wc と同じく次の 8 の倍数の位置まで進める
+// UTF-8 として不正なバイト列は置換文字 (幅 1) として数える
+fn display_width(bytes: &[u8]) -> usize {
+    String::from_utf8_lossy(bytes)
+        .chars()
+        .fold(0, |width, c| match c {
+            '\t' => width + 8 - width % 8,
+            _ => width + c.width().unwrap_or(0),
+        })
+}
+
 // configの設定がtrueになっているフィールドだけ {:>8} のフォーマットで左から並べ、ファイル名があれば添えて出力する
-fn print_info(config: &Config, file_info: &FileInfo, filename: Option<&str>) {
-    let mut format = String::from("");
-    if config.lines {
-        format += &format!("{:>8}", file_info.num_lines);
-    }
-    if config.words {
-        format += &format!("{:>8}", file_info.num_words);
-    }
-    match config.bytes_or_chars {
-        ByteOrChar::Byte => { format += &format!("{:>8}", file_info.num_bytes); },
-        ByteOrChar::Char => { format += &format!("{:>8}", file_info.num_chars); },
-        ByteOrChar::None => {},
+// --json のときは出力せずに results にためておき、最後にまとめて配列として出力する
+fn print_info(config: &Config, file_info: &FileInfo, filename: Option<&str>, results: &mut Vec<Value>) {
+    if config.json {
+        results.push(file_info.to_json(filename.unwrap_or("-"), &config.selection));
+        return;
     }
 
+    let format = file_info.format(&config.selection);
+
     match filename {
         Some(filename) => println!("{} {}", format, filename),
         None => println!("{}", format),
@@ -182,32 +341,58 @@ fn print_info(config: &Config, file_info: &FileInfo, filename: Option<&str>) {
 }
 
 pub fn run(config: Config) -> MyResult<()> {
+    let mut results = Vec::new();
+
     match &config.files {
         None => {
             let buf_reader = BufReader::new(io::stdin());
             let file_info = count(buf_reader)?;
-            print_info(&config, &file_info, None);
+            if config.filter.matches(&file_info) {
+                print_info(&config, &file_info, None, &mut results);
+            }
             
         },
         Some(files) => {
             let mut total_info = FileInfo::zero();
+            // total に含めたファイルの数
+            let mut num_totaled = 0;
+            // "-" (stdin) もほかのファイルと同じく total に含め、名前は "-" と表示する
             for filename in files {
-                match open(&filename) {
+                // ディレクトリは読まずにメッセージだけ出して、total にも含めない
+                if filename != "-" && fs::metadata(filename).is_ok_and(|m| m.is_dir()) {
+                    eprintln!("{}: Is a directory", filename);
+                    continue;
+                }
+                match open(filename) {
                     Err(err) => eprintln!("{}: {}", filename, err),
                     Ok(buf_reader) => {
                         let file_info = count(buf_reader)?;
-                        total_info += &file_info;
-                        print_info(&config, &file_info, Some(&filename));
-
+                        let matched = config.filter.matches(&file_info);
+                        if matched || config.filter.total_all {
+                            total_info += &file_info;
+                            num_totaled += 1;
+                        }
+                        if matched {
+                            print_info(&config, &file_info, Some(filename), &mut results);
+                        }
                     }
                 }
             }
-            // fileが複数指定されていた場合はtotalを表示する
-            if files.len() > 1 {
-                print_info(&config, &total_info, Some("total"));
+            // fileが複数指定されていた場合はtotalを表示する (--no-total なら表示しない)
+            if files.len() > 1 && !config.no_total {
+                print_info(&config, &total_info, Some("total"), &mut results);
+            }
+            // average は total をそれに含めたファイルの数で割ったもの
+            if config.average && num_totaled > 0 {
+                let average_info = total_info.average(num_totaled);
+                print_info(&config, &average_info, Some("average"), &mut results);
             }
         }
     }
+
+    if config.json {
+        println!("{}", Value::Array(results));
+    }
     Ok(())
 }
 
@@ -215,8 +400,9 @@ pub fn run(config: Config) -> MyResult<()> {
 
 #[cfg(test)]
 mod tests {
-    use super::{count, FileInfo};
-    use std::io::Cursor;
+    use super::{count, display_width, FileInfo, Selection};
+    use serde_json::json;
+    use std::io::{BufReader, Cursor};
 
     #[test]
     fn test_count() {
@@ -228,8 +414,153 @@ mod tests {
             num_words: 10,
             num_chars: 48,
             num_bytes: 48,
+            max_line_length: 46,
         };
         assert_eq!(info.unwrap(), expected);
     }
-}
 
+    #[test]
+    fn test_count_form_feed_and_vertical_tab() {
+        let info = count(Cursor::new("one\x0ctwo\x0bthree\n")).unwrap();
+        assert_eq!(info.num_words, 3);
+        assert_eq!(info.num_lines, 1);
+    }
+
+    #[test]
+    fn test_count_no_trailing_newline() {
+        // 最後の行に改行がない場合、行数には数えないが単語は数える
+        let info = count(Cursor::new("hello world\nlast  words")).unwrap();
+        assert_eq!(info.num_lines, 1);
+        assert_eq!(info.num_words, 4);
+        assert_eq!(info.num_bytes, 23);
+        assert_eq!(info.max_line_length, 11);
+    }
+
+    #[test]
+    fn test_count_across_reads() {
+        // バッファの境界をまたぐ単語も1つと数える
+        let reader = BufReader::with_capacity(4, Cursor::new("abcdefgh ijklmnop\n"));
+        let info = count(reader).unwrap();
+        assert_eq!(info.num_words, 2);
+        assert_eq!(info.num_chars, 18);
+    }
+
+    #[test]
+    fn test_count_invalid_utf8() {
+        let info = count(Cursor::new(b"caf\xe9 au lait\n".to_vec())).unwrap();
+        assert_eq!(info.num_words, 3);
+        assert_eq!(info.num_bytes, 13);
+    }
+
+    #[test]
+    fn test_display() {
+        let info = count(Cursor::new("I don't want the world.\nαβγ\n")).unwrap();
+        assert_eq!(info.to_string(), "       2       6      28      31");
+    }
+
+    #[test]
+    fn test_format_selection() {
+        let info = count(Cursor::new("αβγ δ\n")).unwrap();
+        let selection = Selection {
+            words: true,
+            chars: true,
+            ..Selection::default()
+        };
+        assert_eq!(info.format(&selection), "       2       6");
+        assert_eq!(info.format(&Selection::default()), "");
+    }
+
+    #[test]
+    fn test_count_max_line_length() {
+        let text = "short\na much longer line\r\n\nmid-size\n";
+        let info = count(Cursor::new(text)).unwrap();
+        assert_eq!(info.max_line_length, 18);
+
+        // 複数バイトの文字も1文字として数える
+        let info = count(Cursor::new("αβγ\nab")).unwrap();
+        assert_eq!(info.max_line_length, 3);
+
+        // 全角文字は幅 2 として数える
+        let info = count(Cursor::new("日本語\nabcde\n")).unwrap();
+        assert_eq!(info.max_line_length, 6);
+        let info = count(Cursor::new("abc漢字def\n")).unwrap();
+        assert_eq!(info.max_line_length, 10);
+    }
+
+    #[test]
+    fn test_display_width() {
+        assert_eq!(display_width(b""), 0);
+        assert_eq!(display_width("hello".as_bytes()), 5);
+        assert_eq!(display_width("ＡＢＣ".as_bytes()), 6);
+        assert_eq!(display_width("abc漢字def".as_bytes()), 10);
+        // 結合文字は幅を持たない
+        assert_eq!(display_width("e\u{301}".as_bytes()), 1);
+        // 不正なバイトは置換文字として幅 1
+        assert_eq!(display_width(b"caf\xe9"), 4);
+        // タブは次の 8 の倍数の位置まで進め、その他の制御文字は幅を持たない
+        assert_eq!(display_width(b"a\tb"), 9);
+        assert_eq!(display_width(b"\t"), 8);
+        assert_eq!(display_width(b"12345678\tb"), 17);
+        assert_eq!(display_width("漢\tb".as_bytes()), 9);
+        assert_eq!(display_width(b"a\x07b"), 2);
+    }
+
+    #[test]
+    fn test_add_takes_max_line_length() {
+        let mut total = count(Cursor::new("abc\n")).unwrap();
+        total += &count(Cursor::new("a\nabcdefg\n")).unwrap();
+        assert_eq!(total.num_lines, 3);
+        assert_eq!(total.max_line_length, 7);
+    }
+
+    #[test]
+    fn test_average() {
+        let total = FileInfo {
+            num_lines: 5,
+            num_words: 38,
+            num_bytes: 225,
+            num_chars: 224,
+            max_line_length: 46,
+        };
+        let expected = FileInfo {
+            num_lines: 2,
+            num_words: 13,
+            num_bytes: 75,
+            num_chars: 75,
+            max_line_length: 46,
+        };
+        assert_eq!(total.average(3), expected);
+    }
+
+    #[test]
+    fn test_to_json() {
+        let info = FileInfo {
+            num_lines: 1,
+            num_words: 10,
+            num_chars: 46,
+            num_bytes: 48,
+            max_line_length: 46,
+        };
+
+        let selection = Selection {
+            lines: true,
+            words: true,
+            bytes: true,
+            ..Selection::default()
+        };
+        assert_eq!(
+            info.to_json("a \"b\".txt", &selection),
+            json!({ "file": "a \"b\".txt", "lines": 1, "words": 10, "bytes": 48 })
+        );
+
+        let selection = Selection {
+            chars: true,
+            max_line_length: true,
+            ..Selection::default()
+        };
+        assert_eq!(
+            info.to_json("-", &selection),
+            json!({ "file": "-", "chars": 46, "max_line_length": 46 })
+        );
+    }
+}
@@ -9,6 +9,7 @@ const PRG: &str = "wcr";
 const EMPTY: &str = "tests/inputs/empty.txt";
 const FOX: &str = "tests/inputs/fox.txt";
 const ATLAMAL: &str = "tests/inputs/atlamal.txt";
+const LINES: &str = "tests/inputs/lines.txt";
 
 // --------------------------------------------------
 fn gen_bad_file() -> String {
@@ -29,7 +30,7 @@ fn gen_bad_file() -> String {
 #[test]
 fn dies_chars_and_bytes() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&["-m", "-c"])
+        .args(["-m", "-c"])
         .assert()
         .failure()
         .stderr(predicate::str::contains(
@@ -212,3 +213,229 @@ fn test_all_words_lines() -> TestResult {
 fn test_all_bytes_lines() -> TestResult {
     run(&["-cl", EMPTY, FOX, ATLAMAL], "tests/expected/all.cl.out")
 }
+
+// --------------------------------------------------
+#[test]
+fn test_all_min_lines() -> TestResult {
+    run(
+        &["--min-lines", "1", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.min1.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_min_max_lines() -> TestResult {
+    run(
+        &["--min-lines", "1", "--max-lines", "3", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.min1.max3.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_max_lines_total_all() -> TestResult {
+    run(
+        &["--max-lines", "1", "--total-all", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.max1.total_all.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn lines_max_line_length() -> TestResult {
+    run(&["-L", LINES], "tests/expected/lines.txt.L.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn lines_empty_lines_max_line_length() -> TestResult {
+    run(&["-l", "-L", LINES, EMPTY], "tests/expected/lines_empty.lL.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn stdin_between_files() -> TestResult {
+    let input = fs::read_to_string(ATLAMAL)?;
+    let expected = fs::read_to_string("tests/expected/fox_stdin_lines.out")?;
+    Command::cargo_bin(PRG)?
+        .args([FOX, "-", LINES])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn test_json_two_files() -> TestResult {
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["--json", FOX, ATLAMAL])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let value: serde_json::Value = serde_json::from_str(&stdout)?;
+    assert_eq!(
+        value,
+        serde_json::json!([
+            { "file": FOX, "lines": 1, "words": 9, "bytes": 48 },
+            { "file": ATLAMAL, "lines": 4, "words": 29, "bytes": 177 },
+            { "file": "total", "lines": 5, "words": 38, "bytes": 225 },
+        ])
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn test_json_stdin_selected() -> TestResult {
+    let input = fs::read_to_string(FOX)?;
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["--json", "-l", "-m"])
+        .write_stdin(input)
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let value: serde_json::Value = serde_json::from_str(&stdout)?;
+    assert_eq!(value, serde_json::json!([{ "file": "-", "lines": 1, "chars": 48 }]));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn test_average_three_files() -> TestResult {
+    let expected = fs::read_to_string("tests/expected/fox_atlamal_empty.average.out")?;
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["--average", FOX, ATLAMAL, EMPTY])
+        .assert()
+        .success()
+        .stdout(expected);
+    // 5 / 3, 38 / 3, 225 / 3 を四捨五入したもの
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    assert_eq!(stdout.lines().last(), Some("       2      13      75 average"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn test_average_single_file() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--average", FOX])
+        .assert()
+        .success()
+        .stdout("       1       9      48 tests/inputs/fox.txt\n       1       9      48 average\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn skips_directory() -> TestResult {
+    let expected = fs::read_to_string("tests/expected/fox_atlamal_empty.average.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["--average", FOX, "tests/inputs", ATLAMAL, EMPTY])
+        .assert()
+        .success()
+        .stdout(expected)
+        .stderr("tests/inputs: Is a directory\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn stdin_included_in_total() -> TestResult {
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["--json", FOX, "-"])
+        .write_stdin("one two\nthree\n")
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let value: serde_json::Value = serde_json::from_str(&stdout)?;
+    assert_eq!(
+        value,
+        serde_json::json!([
+            { "file": FOX, "lines": 1, "words": 9, "bytes": 48 },
+            { "file": "-", "lines": 2, "words": 3, "bytes": 14 },
+            { "file": "total", "lines": 3, "words": 12, "bytes": 62 },
+        ])
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn stdin_twice_drains_stdin() -> TestResult {
+    // 最初の "-" で stdin を読み切るので、2 回目の "-" は空になる
+    Command::cargo_bin(PRG)?
+        .args(["-", FOX, "-"])
+        .write_stdin("one two\nthree\n")
+        .assert()
+        .success()
+        .stdout(
+            "       2       3      14 -\n       \
+             1       9      48 tests/inputs/fox.txt\n       \
+             0       0       0 -\n       \
+             3      12      62 total\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn max_line_length_wide_chars() -> TestResult {
+    // 全角文字は幅 2 として数える
+    Command::cargo_bin(PRG)?
+        .args(["-L"])
+        .write_stdin("abc漢字def\nＡＢＣ\n")
+        .assert()
+        .success()
+        .stdout("      10\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn max_line_length_tab() -> TestResult {
+    // GNU wc と同じく、タブは次の 8 の倍数の位置まで進める
+    Command::cargo_bin(PRG)?
+        .args(["-L"])
+        .write_stdin("a\tb\n")
+        .assert()
+        .success()
+        .stdout("       9\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn no_total_two_files() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--no-total", FOX, ATLAMAL])
+        .assert()
+        .success()
+        .stdout(
+            "       1       9      48 tests/inputs/fox.txt\n       \
+             4      29     177 tests/inputs/atlamal.txt\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn no_total_json_with_average() -> TestResult {
+    // total の行だけを出さず、average はそのまま出す
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["--no-total", "--json", "--average", FOX, ATLAMAL])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let value: serde_json::Value = serde_json::from_str(&stdout)?;
+    let files: Vec<_> = value
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["file"].as_str().unwrap())
+        .collect();
+    assert_eq!(files, [FOX, ATLAMAL, "average"]);
+    Ok(())
+}